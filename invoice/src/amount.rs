@@ -278,6 +278,44 @@ impl Precision {
     pub fn saturating_convert(self, amount: impl Into<u64>) -> Amount {
         amount.into().saturating_mul(self.multiplier()).into()
     }
+
+    /// Parses human-readable decimal amount, like `12.345` or `1 000.5 USDT`,
+    /// into atomic units according to the precision. Digit group separators
+    /// and a trailing ticker are ignored.
+    ///
+    /// # Errors
+    ///
+    /// If the amount contains characters other than decimal digits and a
+    /// decimal point, including signs.
+    ///
+    /// If the amount has more significant fractional digits than allowed by
+    /// the precision, or if its atomic value doesn't fit into 64 bits.
+    pub fn parse_amount(self, s: &str) -> Result<Amount, AmountParseError> {
+        let s = s.replace([' ', '_', '`', '\''], "");
+        let s = s.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+        // Integer parsing accepts a leading sign, which is not valid in amounts
+        if let Some(c) = s.chars().find(|c| !c.is_ascii_digit() && *c != '.') {
+            return Err(AmountParseError::InvalidChar(c));
+        }
+        let (int, fract) = s.split_once('.').unwrap_or((s, ""));
+        let int: u64 = int.parse().map_err(AmountParseError::InvalidInt)?;
+        let fract = fract.trim_end_matches('0');
+        let decimals = self.decimals();
+        if fract.len() > decimals as usize {
+            return Err(AmountParseError::ExcessivePrecision(decimals));
+        }
+        let fract = match fract {
+            "" => 0,
+            fract => {
+                let value: u64 = fract.parse().map_err(AmountParseError::InvalidFract)?;
+                value * 10u64.pow((decimals as usize - fract.len()) as u32)
+            }
+        };
+        int.checked_mul(self.multiplier())
+            .and_then(|int| int.checked_add(fract))
+            .map(Amount::from)
+            .ok_or(AmountParseError::Overflow)
+    }
 }
 
 impl From<Precision> for u16 {
@@ -413,6 +451,8 @@ pub enum AmountParseError {
     InvalidInt(ParseIntError),
     /// invalid amount fractional part - {0}
     InvalidFract(ParseIntError),
+    /// invalid character '{0}' in amount
+    InvalidChar(char),
     /// invalid amount precision - {0}
     InvalidPrecision(ParseIntError),
 
@@ -423,6 +463,13 @@ pub enum AmountParseError {
     /// invalid amount precision exceeding 18
     #[from]
     UnknownPrecision(VariantError<u8>),
+
+    /// amount has more fractional digits than allowed by the precision of {0}
+    /// decimals.
+    ExcessivePrecision(u8),
+
+    /// amount exceeds the maximum value.
+    Overflow,
 }
 
 impl FromStr for CoinAmount {
@@ -484,6 +531,26 @@ mod test {
         assert_eq!(format!("{amount:_>#}"), "10.00_000_500");
     }
 
+    #[test]
+    fn parse_amount() {
+        let precision = Precision::Milli;
+        assert_eq!(precision.parse_amount("12.345 USDT"), Ok(Amount::from(12_345u64)));
+        assert_eq!(precision.parse_amount("1 000.5"), Ok(Amount::from(1_000_500u64)));
+        assert_eq!(precision.parse_amount("0.0100"), Ok(Amount::from(10u64)));
+        assert_eq!(precision.parse_amount("7"), Ok(Amount::from(7_000u64)));
+        assert_eq!(precision.parse_amount("12.3456"), Err(AmountParseError::ExcessivePrecision(3)));
+        assert_eq!(
+            Precision::Indivisible.parse_amount("1.5"),
+            Err(AmountParseError::ExcessivePrecision(0))
+        );
+        assert_eq!(Precision::Atto.parse_amount("19"), Err(AmountParseError::Overflow));
+        assert!(matches!(precision.parse_amount("1.2.3"), Err(AmountParseError::InvalidFract(_))));
+        assert_eq!(precision.parse_amount("+1"), Err(AmountParseError::InvalidChar('+')));
+        assert_eq!(precision.parse_amount("1.+5"), Err(AmountParseError::InvalidChar('+')));
+        assert_eq!(precision.parse_amount("-1"), Err(AmountParseError::InvalidChar('-')));
+        assert_eq!(precision.parse_amount("1.-5"), Err(AmountParseError::InvalidChar('-')));
+    }
+
     #[test]
     #[allow(clippy::inconsistent_digit_grouping)]
     fn zero_fraction() {
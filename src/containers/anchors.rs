@@ -26,6 +26,7 @@ use amplify::ByteArray;
 use bp::dbc::opret::OpretProof;
 use bp::dbc::tapret::TapretProof;
 use bp::dbc::{anchor, Anchor};
+use bp::seals::txout::CloseMethod;
 use bp::{Tx, Txid};
 use commit_verify::{mpc, CommitId, ReservedBytes};
 use rgb::{
@@ -34,6 +35,7 @@ use rgb::{
 };
 use strict_encoding::StrictDumb;
 
+use crate::containers::ConsistencyError;
 use crate::{BundleExt, MergeReveal, MergeRevealError, RevealError, LIB_NAME_RGB_STD};
 
 #[derive(Clone, Eq, PartialEq, Debug)]
@@ -341,6 +343,24 @@ impl<P: mpc::Proof + StrictDumb> AnchoredBundles<P> {
 
     pub fn has_opret(&self) -> bool { matches!(self, Self::Opret(..) | Self::Double { .. }) }
 
+    /// Returns the close method of the anchor committing to the bundle with
+    /// the given id, or `None` if no such bundle is present.
+    pub fn close_method(&self, bundle_id: BundleId) -> Option<CloseMethod> {
+        match self {
+            AnchoredBundles::Tapret(_, bundle) |
+            AnchoredBundles::Double {
+                tapret_bundle: bundle,
+                ..
+            } if bundle.bundle_id() == bundle_id => Some(CloseMethod::TapretFirst),
+            AnchoredBundles::Opret(_, bundle) |
+            AnchoredBundles::Double {
+                opret_bundle: bundle,
+                ..
+            } if bundle.bundle_id() == bundle_id => Some(CloseMethod::OpretFirst),
+            _ => None,
+        }
+    }
+
     pub fn pairs(&self) -> vec::IntoIter<(EAnchor<P>, &TransitionBundle)>
     where P: Clone {
         match self {
@@ -512,8 +532,8 @@ impl AnchoredBundles {
             ) if opret_anchor == o => Ok(AnchoredBundles::Double {
                 tapret_anchor,
                 opret_anchor,
-                tapret_bundle: tapret_bundle.merge_reveal(bundle)?,
-                opret_bundle,
+                tapret_bundle,
+                opret_bundle: opret_bundle.merge_reveal(bundle)?,
             }),
 
             (
@@ -545,4 +565,26 @@ impl AnchoredBundles {
             )),
         }
     }
+
+    /// Merges anchored bundles of the same witness coming from different
+    /// consignments into a single entry, combining both the anchors and the
+    /// revealed bundle data.
+    ///
+    /// # Errors
+    ///
+    /// [`ConsistencyError::IncompatibleAnchors`] if the anchors of the same
+    /// type differ, meaning that the consignments provide conflicting proofs
+    /// for the `witness_id`.
+    pub fn merge_anchored(
+        self,
+        other: Self,
+        witness_id: XWitnessId,
+    ) -> Result<Self, ConsistencyError> {
+        self.merge_reveal(other).map_err(|err| match err {
+            MergeRevealError::AnchorsNonEqual(_) => {
+                ConsistencyError::IncompatibleAnchors(witness_id)
+            }
+            err => ConsistencyError::MergeReveal(err),
+        })
+    }
 }
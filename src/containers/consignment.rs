@@ -21,35 +21,44 @@
 
 use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::{Display, Formatter};
-use std::ops::Deref;
 use std::str::FromStr;
 use std::{fmt, iter};
 
-use aluvm::library::Lib;
+use aluvm::library::{Lib, LibId};
 use amplify::confinement::{
-    Confined, LargeOrdSet, MediumBlob, SmallOrdMap, SmallOrdSet, TinyOrdMap, TinyOrdSet,
+    Collection, Confined, LargeOrdSet, MediumBlob, SmallOrdMap, SmallOrdSet, TinyOrdMap, TinyOrdSet,
 };
-use amplify::{ByteArray, Bytes32};
+use amplify::{ByteArray, Bytes32, Wrapper};
 use armor::{ArmorHeader, AsciiArmor, StrictArmor};
 use baid64::{Baid64ParseError, DisplayBaid64, FromBaid64Str};
-use commit_verify::{CommitEncode, CommitEngine, CommitId, CommitmentId, DigestExt, Sha256};
-use rgb::validation::{ResolveWitness, Validator, Validity, Warning, CONSIGNMENT_MAX_LIBS};
+use bp::seals::txout::CloseMethod;
+use bp::Txid;
+use commit_verify::{
+    CommitEncode, CommitEngine, CommitId, CommitmentId, Conceal, DigestExt, Sha256,
+};
+use invoice::Amount;
+use rgb::validation::{ResolveWitness, CONSIGNMENT_MAX_LIBS};
 use rgb::{
-    impl_serde_baid64, validation, AttachId, BundleId, ContractHistory, ContractId, Extension,
-    Genesis, GraphSeal, Operation, Schema, SchemaId, XChain,
+    impl_serde_baid64, validation, Assign, AssignmentType, Assignments, AttachId, BundleId,
+    ContractHistory, ContractId, ContractState, DiscloseHash, ExposedSeal, Extension, Genesis,
+    GraphSeal, OpId, Operation, Opout, Schema, SchemaId, Transition, TransitionBundle,
+    TypedAssigns, ValencyType, WitnessAnchor, XChain, XOutpoint, XWitnessId, XWitnessTx,
+};
+use strict_encoding::{
+    DecodeError, StrictDecode, StrictDeserialize, StrictDumb, StrictEncode, StrictSerialize,
 };
-use strict_encoding::{StrictDeserialize, StrictDumb, StrictSerialize};
-use strict_types::TypeSystem;
+use strict_types::{TypeSysId, TypeSystem};
 
 use super::{
-    BundledWitness, ContainerVer, ContentId, ContentSigs, IndexedConsignment, Supplement, Terminal,
-    TerminalDisclose, ASCII_ARMOR_CONSIGNMENT_TYPE, ASCII_ARMOR_CONTRACT, ASCII_ARMOR_IFACE,
-    ASCII_ARMOR_SCHEMA, ASCII_ARMOR_TERMINAL, ASCII_ARMOR_VERSION,
+    AnchoredBundles, BundledWitness, ConflictingSpend, ContainerVer, ContentId, ContentSigs,
+    PubWitness, SealMethodMismatch, SupplId, Supplement, Terminal, TerminalDisclose, TerminalSeal,
+    ValidConsignment, ValidationPolicy, ASCII_ARMOR_CONSIGNMENT_TYPE, ASCII_ARMOR_CONTRACT,
+    ASCII_ARMOR_IFACE, ASCII_ARMOR_SCHEMA, ASCII_ARMOR_TERMINAL, ASCII_ARMOR_VERSION,
 };
 use crate::containers::anchors::ToWitnessId;
-use crate::interface::{Iface, IfaceImpl};
-use crate::resolvers::ResolveHeight;
-use crate::{BundleExt, SecretSeal, LIB_NAME_RGB_STD};
+use crate::interface::{Iface, IfaceImpl, ImplId};
+use crate::resolvers::{CachedResolver, ResolveHeight};
+use crate::{BundleExt, MergeReveal, MergeRevealError, SecretSeal, LIB_NAME_RGB_STD};
 
 pub type Transfer = Consignment<true>;
 pub type Contract = Consignment<false>;
@@ -95,36 +104,102 @@ impl Display for ConsignmentId {
 impl_serde_baid64!(ConsignmentId);
 
 impl ConsignmentId {
+    /// All-zero id, which may be used as an explicit null sentinel value.
+    pub const ZERO: Self = Self::from_array([0u8; 32]);
+
     pub const fn from_array(id: [u8; 32]) -> Self { Self(Bytes32::from_array(id)) }
-}
 
-pub type ValidContract = ValidConsignment<false>;
-pub type ValidTransfer = ValidConsignment<true>;
+    /// Checks whether the id is the [`ConsignmentId::ZERO`] sentinel.
+    pub fn is_null(&self) -> bool { *self == Self::ZERO }
 
-#[derive(Clone, Debug, Display)]
-#[display("{consignment}")]
-pub struct ValidConsignment<const TRANSFER: bool> {
-    /// Status of the latest validation.
-    validation_status: validation::Status,
-    consignment: Consignment<TRANSFER>,
+    pub fn as_bytes(&self) -> &[u8; 32] { self.0.as_inner() }
+
+    /// Short form of the id for logs and compact UI, made of the first and the
+    /// last chunks of the Baid64 representation (like `poAMvm9j..Tl54Yew`).
+    pub fn fingerprint(&self) -> String {
+        let baid64 = self.to_baid64_string();
+        let (_, payload) = baid64.rsplit_once(':').unwrap_or(("", &baid64));
+        let (payload, _) = payload.split_once('#').unwrap_or((payload, ""));
+        let first = payload.split('-').next().unwrap_or_default();
+        let last = payload.rsplit('-').next().unwrap_or_default();
+        format!("{first}..{last}")
+    }
 }
 
-impl<const TRANSFER: bool> ValidConsignment<TRANSFER> {
-    pub fn validation_status(&self) -> &validation::Status { &self.validation_status }
+/// Errors in consignment data consistency.
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum ConsistencyError {
+    /// consignments for different contracts {0} and {1} can't be merged.
+    ContractMismatch(ContractId, ContractId),
+
+    /// consignment {kind} collection contains {actual} items, exceeding the
+    /// limit of {limit} items.
+    TooLarge {
+        kind: &'static str,
+        limit: usize,
+        actual: usize,
+    },
+
+    /// none of the provided seals is assigned by the consignment state
+    /// transitions.
+    UnrelatedSeals,
+
+    /// operation {0} is referenced by the consignment history, but is absent
+    /// from the consignment.
+    UnknownOperation(OpId),
+
+    /// consignment terminals reference bundle {0} which is absent from the
+    /// consignment.
+    DanglingTerminal(BundleId),
 
-    pub fn into_consignment(self) -> Consignment<TRANSFER> { self.consignment }
+    /// operation {0} contains revealed seals which are not consignment
+    /// terminals; the consignment must be finalized before being sent.
+    NotFinalized(OpId),
 
-    pub fn into_validation_status(self) -> validation::Status { self.validation_status }
+    /// consignments provide conflicting anchors for the witness {0}.
+    IncompatibleAnchors(XWitnessId),
 
-    pub fn split(self) -> (Consignment<TRANSFER>, validation::Status) {
-        (self.consignment, self.validation_status)
-    }
-}
+    /// witness transaction {0} must be provided to verify its commitments.
+    WitnessTxAbsent(Txid),
+
+    /// witness {0} contains transaction with a different id {1}.
+    WitnessTxMismatch(Txid, Txid),
 
-impl<const TRANSFER: bool> Deref for ValidConsignment<TRANSFER> {
-    type Target = Consignment<TRANSFER>;
+    /// witness transaction {0} doesn't commit to the bundle {1}.
+    WitnessCommitmentMismatch(Txid, BundleId),
 
-    fn deref(&self) -> &Self::Target { &self.consignment }
+    /// seal {0} requested to be exposed is not a consignment terminal.
+    UnknownExposeTerminal(XChain<TerminalSeal>),
+
+    /// sum of the fungible state of type {0} exceeds the maximum value.
+    ValueOverflow(AssignmentType),
+
+    /// consignment contains more {kind} than allowed by the decoding limit of
+    /// {limit}.
+    DecodeLimitExceeded { kind: &'static str, limit: usize },
+
+    #[from]
+    #[display(inner)]
+    Decode(DecodeError),
+
+    #[from]
+    #[display(inner)]
+    MergeReveal(MergeRevealError),
+}
+
+/// Converts collection into a confined one, reporting
+/// [`ConsistencyError::TooLarge`] if the collection exceeds the confinement.
+fn confine<C: Collection, const MIN: usize, const MAX: usize>(
+    kind: &'static str,
+    col: C,
+) -> Result<Confined<C, MIN, MAX>, ConsistencyError> {
+    let actual = col.len();
+    Confined::try_from(col).map_err(|_| ConsistencyError::TooLarge {
+        kind,
+        limit: MAX,
+        actual,
+    })
 }
 
 /// Consignment represents contract-specific data, always starting with genesis,
@@ -199,42 +274,188 @@ impl<const TRANSFER: bool> CommitEncode for Consignment<TRANSFER> {
     type CommitmentId = ConsignmentId;
 
     fn commit_encode(&self, e: &mut CommitEngine) {
+        ConsignmentCommitment {
+            version: self.version,
+            transfer: self.transfer,
+            contract_id: self.contract_id(),
+            genesis: self.genesis.disclose_hash(),
+            ifaces: TinyOrdSet::from_iter_unsafe(self.ifaces.values().map(IfaceImpl::impl_id)),
+            bundles: LargeOrdSet::from_iter_unsafe(
+                self.bundles.iter().map(BundledWitness::disclose_hash),
+            ),
+            extensions: LargeOrdSet::from_iter_unsafe(
+                self.extensions.iter().map(Extension::disclose_hash),
+            ),
+            terminals: SmallOrdSet::from_iter_unsafe(self.terminals_disclose()),
+            attachments: SmallOrdSet::from_iter_unsafe(self.attachments.keys().copied()),
+            supplements: TinyOrdSet::from_iter_unsafe(
+                self.supplements.iter().map(Supplement::suppl_id),
+            ),
+            types: self.types.id(),
+            scripts: SmallOrdSet::from_iter_unsafe(self.scripts.iter().map(Lib::id)),
+            signatures: &self.signatures,
+        }
+        .commit_encode(e)
+    }
+}
+
+/// Data committed by the [`ConsignmentId`], shared by the in-memory
+/// [`CommitEncode`] implementation and the streaming
+/// [`Consignment::consignment_id_streaming`], so both always produce the same
+/// id.
+pub(super) struct ConsignmentCommitment<'sigs> {
+    pub(super) version: ContainerVer,
+    pub(super) transfer: bool,
+    pub(super) contract_id: ContractId,
+    pub(super) genesis: DiscloseHash,
+    pub(super) ifaces: TinyOrdSet<ImplId>,
+    pub(super) bundles: LargeOrdSet<DiscloseHash>,
+    pub(super) extensions: LargeOrdSet<DiscloseHash>,
+    pub(super) terminals: SmallOrdSet<TerminalDisclose>,
+    pub(super) attachments: SmallOrdSet<AttachId>,
+    pub(super) supplements: TinyOrdSet<SupplId>,
+    pub(super) types: TypeSysId,
+    pub(super) scripts: SmallOrdSet<LibId>,
+    pub(super) signatures: &'sigs TinyOrdMap<ContentId, ContentSigs>,
+}
+
+impl ConsignmentCommitment<'_> {
+    pub(super) fn commit_encode(&self, e: &mut CommitEngine) {
         e.commit_to_serialized(&self.version);
         e.commit_to_serialized(&self.transfer);
 
-        e.commit_to_serialized(&self.contract_id());
-        e.commit_to_serialized(&self.genesis.disclose_hash());
-        e.commit_to_set(&TinyOrdSet::from_iter_unsafe(
-            self.ifaces.values().map(|iimpl| iimpl.impl_id()),
-        ));
+        e.commit_to_serialized(&self.contract_id);
+        e.commit_to_serialized(&self.genesis);
+        e.commit_to_set(&self.ifaces);
 
-        e.commit_to_set(&LargeOrdSet::from_iter_unsafe(
-            self.bundles.iter().map(BundledWitness::disclose_hash),
-        ));
-        e.commit_to_set(&LargeOrdSet::from_iter_unsafe(
-            self.extensions.iter().map(Extension::disclose_hash),
-        ));
-        e.commit_to_set(&SmallOrdSet::from_iter_unsafe(self.terminals_disclose()));
+        e.commit_to_set(&self.bundles);
+        e.commit_to_set(&self.extensions);
+        e.commit_to_set(&self.terminals);
 
-        e.commit_to_set(&SmallOrdSet::from_iter_unsafe(self.attachments.keys().copied()));
-        e.commit_to_set(&TinyOrdSet::from_iter_unsafe(
-            self.supplements.iter().map(|suppl| suppl.suppl_id()),
-        ));
+        e.commit_to_set(&self.attachments);
+        e.commit_to_set(&self.supplements);
 
-        e.commit_to_serialized(&self.types.id());
-        e.commit_to_set(&SmallOrdSet::from_iter_unsafe(self.scripts.iter().map(|lib| lib.id())));
+        e.commit_to_serialized(&self.types);
+        e.commit_to_set(&self.scripts);
 
-        e.commit_to_map(&self.signatures);
+        e.commit_to_map(self.signatures);
     }
 }
 
 impl<const TRANSFER: bool> Consignment<TRANSFER> {
+    /// Composes consignment from the contract genesis and history retrieved
+    /// from external storage by walking from the terminal operations towards
+    /// the genesis.
+    ///
+    /// Each terminal is given as an id of a state transition together with
+    /// the seal it assigns state to. The `resolve` callback must return
+    /// bundled witness containing the revealed state transition with the
+    /// provided id.
+    ///
+    /// The composed consignment does not contain interfaces, types, scripts
+    /// or any other data beyond the schema, genesis and the state history.
+    ///
+    /// # Errors
+    ///
+    /// If some of the transitions can't be resolved, or if a terminal seal is
+    /// not assigned by its terminal transition.
+    pub fn compose(
+        schema: Schema,
+        genesis: Genesis,
+        terminals: &[(OpId, XChain<TerminalSeal>)],
+        mut resolve: impl FnMut(OpId) -> Option<BundledWitness>,
+    ) -> Result<Self, ConsistencyError> {
+        let genesis_id = genesis.id();
+        let mut bundles = BTreeMap::<XWitnessId, BundledWitness>::new();
+        let mut known = BTreeSet::new();
+        let mut ids = vec![];
+        let mut resolve_op = |opid: OpId| -> Result<(BundleId, Transition), ConsistencyError> {
+            let bw = resolve(opid).ok_or(ConsistencyError::UnknownOperation(opid))?;
+            let (bundle_id, transition) = bw
+                .bundles()
+                .find_map(|bundle| {
+                    let transition = bundle.known_transitions.get(&opid)?;
+                    Some((bundle.bundle_id(), transition.clone()))
+                })
+                .ok_or(ConsistencyError::UnknownOperation(opid))?;
+            let witness_id = bw.witness_id();
+            let bw = match bundles.remove(&witness_id) {
+                Some(mut prev) => {
+                    prev.pub_witness = prev.pub_witness.merge_reveal(bw.pub_witness)?;
+                    prev.anchored_bundles = prev
+                        .anchored_bundles
+                        .merge_anchored(bw.anchored_bundles, witness_id)?;
+                    prev
+                }
+                None => bw,
+            };
+            bundles.insert(witness_id, bw);
+            Ok((bundle_id, transition))
+        };
+
+        let mut terminal_map = BTreeMap::<BundleId, BTreeSet<XChain<TerminalSeal>>>::new();
+        for (opid, seal) in terminals {
+            let (bundle_id, transition) = resolve_op(*opid)?;
+            let secret = seal.conceal();
+            if !transition
+                .assignments
+                .values()
+                .any(|assigns| assigns.to_confidential_seals().contains(&secret))
+            {
+                return Err(ConsistencyError::UnrelatedSeals);
+            }
+            terminal_map.entry(bundle_id).or_default().insert(*seal);
+            if known.insert(*opid) {
+                ids.extend(transition.inputs().iter().map(|input| input.prev_out.op));
+            }
+        }
+        while let Some(opid) = ids.pop() {
+            if opid == genesis_id || !known.insert(opid) {
+                continue;
+            }
+            let (_, transition) = resolve_op(opid)?;
+            ids.extend(transition.inputs().iter().map(|input| input.prev_out.op));
+        }
+
+        let terminals = terminal_map
+            .into_iter()
+            .map(|(bundle_id, seals)| {
+                let seals = confine("terminal seals", seals)?;
+                Ok((bundle_id, Terminal { seals }))
+            })
+            .collect::<Result<BTreeMap<_, _>, ConsistencyError>>()?;
+
+        Ok(Consignment {
+            version: ContainerVer::V2,
+            transfer: TRANSFER,
+            terminals: confine("terminals", terminals)?,
+            genesis,
+            extensions: none!(),
+            bundles: confine("bundles", bundles.into_values().collect::<BTreeSet<_>>())?,
+            schema,
+            ifaces: none!(),
+            supplements: none!(),
+            types: none!(),
+            scripts: none!(),
+            attachments: none!(),
+            signatures: none!(),
+        })
+    }
+
     #[inline]
     pub fn consignment_id(&self) -> ConsignmentId { self.commit_id() }
 
+    /// Short form of the consignment id (see [`ConsignmentId::fingerprint`]).
+    #[inline]
+    pub fn fingerprint(&self) -> String { self.consignment_id().fingerprint() }
+
     #[inline]
     pub fn schema_id(&self) -> SchemaId { self.schema.schema_id() }
 
+    /// Checks whether the consignment uses schema with the `expected` id.
+    #[inline]
+    pub fn matches_schema(&self, expected: SchemaId) -> bool { self.schema_id() == expected }
+
     #[inline]
     pub fn contract_id(&self) -> ContractId { self.genesis.contract_id() }
 
@@ -245,6 +466,13 @@ impl<const TRANSFER: bool> Consignment<TRANSFER> {
             .flat_map(|(id, term)| term.secrets().map(move |secret| (id, secret)))
     }
 
+    /// Iterates over the terminal seals of all consignment terminals.
+    pub fn terminal_seals(&self) -> impl Iterator<Item = XChain<TerminalSeal>> + '_ {
+        self.terminals
+            .values()
+            .flat_map(|terminal| terminal.seals.iter().copied())
+    }
+
     pub fn terminals_disclose(&self) -> impl Iterator<Item = TerminalDisclose> + '_ {
         self.terminals.iter().flat_map(|(id, term)| {
             term.seals.iter().map(|seal| TerminalDisclose {
@@ -254,6 +482,539 @@ impl<const TRANSFER: bool> Consignment<TRANSFER> {
         })
     }
 
+    /// Splits consignment into sub-consignments, each containing only bundles
+    /// anchored with a single close method, together with their terminals.
+    /// Bundles anchored with both methods at once are put under `None` key.
+    ///
+    /// All sub-consignments share the same genesis, extensions, schema and
+    /// other contract data.
+    pub fn partition_by_close_method(&self) -> BTreeMap<Option<CloseMethod>, Self> {
+        let mut partitions = BTreeMap::<_, BTreeSet<_>>::new();
+        for bw in &self.bundles {
+            let method = match bw.anchored_bundles {
+                AnchoredBundles::Tapret(..) => Some(CloseMethod::TapretFirst),
+                AnchoredBundles::Opret(..) => Some(CloseMethod::OpretFirst),
+                AnchoredBundles::Double { .. } => None,
+            };
+            partitions.entry(method).or_default().insert(bw.clone());
+        }
+        partitions
+            .into_iter()
+            .map(|(method, bundles)| {
+                let bundle_ids = bundles
+                    .iter()
+                    .flat_map(|bw| bw.bundles().map(TransitionBundle::bundle_id))
+                    .collect::<BTreeSet<_>>();
+                let mut consignment = self.clone();
+                consignment.bundles = LargeOrdSet::from_collection_unsafe(bundles);
+                consignment.terminals = SmallOrdMap::from_iter_unsafe(
+                    self.terminals
+                        .iter()
+                        .filter(|(bundle_id, _)| bundle_ids.contains(*bundle_id))
+                        .map(|(bundle_id, terminal)| (*bundle_id, terminal.clone())),
+                );
+                (method, consignment)
+            })
+            .collect()
+    }
+
+    /// Computes size of the client-side data anchored in each of the witness
+    /// transactions, i.e. total strict-encoded length of the bundles committed
+    /// by the witness. The on-chain commitment itself has a constant size.
+    pub fn anchor_commitment_sizes(&self) -> BTreeMap<XWitnessId, usize> {
+        self.bundles
+            .iter()
+            .map(|bw| (bw.witness_id(), bw.bundles().map(BundleExt::strict_weight).sum()))
+            .collect()
+    }
+
+    /// Sums revealed fungible state of the given type assigned to the terminal
+    /// seals of the consignment.
+    ///
+    /// # Errors
+    ///
+    /// [`ConsistencyError::ValueOverflow`] if the sum exceeds the maximum
+    /// fungible value.
+    pub fn total_by_type(&self, ty: AssignmentType) -> Result<Amount, ConsistencyError> {
+        let secrets = self
+            .terminal_seals()
+            .map(|seal| seal.conceal())
+            .collect::<BTreeSet<_>>();
+        let mut total = Amount::ZERO;
+        for transition in self.terminal_transitions_by_assignment_type(ty) {
+            let Some(assigns) = transition.assignments.get(&ty) else {
+                continue;
+            };
+            for (seal, state) in assigns.as_fungible().iter().filter_map(Assign::as_revealed) {
+                if secrets.contains(&seal.conceal()) {
+                    total = total
+                        .checked_add(state.value.as_u64())
+                        .ok_or(ConsistencyError::ValueOverflow(ty))?;
+                }
+            }
+        }
+        Ok(total)
+    }
+
+    /// Lists state transitions from the terminal bundles which assign state of
+    /// the given type.
+    ///
+    /// Consignment endpoints are called terminals in this library, and owned
+    /// right types are called assignment types.
+    #[doc(alias = "endpoint_transitions_by_owned_right")]
+    pub fn terminal_transitions_by_assignment_type(&self, ty: AssignmentType) -> Vec<&Transition> {
+        self.bundles
+            .iter()
+            .flat_map(|bw| bw.bundles())
+            .filter(|bundle| self.terminals.contains_key(&bundle.bundle_id()))
+            .flat_map(|bundle| bundle.known_transitions.values())
+            .filter(|transition| transition.assignments.contains_key(&ty))
+            .collect()
+    }
+
+    /// Lists valencies (public rights) defined by the consignment operations,
+    /// which are not redeemed by any of its state extensions.
+    pub fn open_valencies(&self) -> BTreeSet<(OpId, ValencyType)> {
+        let transitions = self
+            .bundles
+            .iter()
+            .flat_map(|bw| bw.bundles())
+            .flat_map(|bundle| bundle.known_transitions.values())
+            .map(|transition| (transition.id(), transition.valencies()));
+        let extensions = self
+            .extensions
+            .iter()
+            .map(|extension| (extension.id(), extension.valencies()));
+        let mut valencies = iter::once((self.genesis.id(), self.genesis.valencies()))
+            .chain(transitions)
+            .chain(extensions)
+            .flat_map(|(opid, valencies)| valencies.iter().map(move |ty| (opid, *ty)))
+            .collect::<BTreeSet<_>>();
+        for extension in &self.extensions {
+            for (ty, opid) in extension.redeemed() {
+                valencies.remove(&(*opid, *ty));
+            }
+        }
+        valencies
+    }
+
+    /// Collects outpoints of the seals closed by the consignment state
+    /// transitions. Seals which are concealed, belong to operations absent
+    /// from the consignment or point to an unknown witness transaction are
+    /// skipped.
+    pub fn spent_outpoints(&self) -> BTreeSet<XOutpoint> {
+        let mut transitions = BTreeMap::new();
+        for bw in &self.bundles {
+            let witness_id = bw.witness_id();
+            for bundle in bw.bundles() {
+                for (opid, transition) in &bundle.known_transitions {
+                    transitions.insert(*opid, (witness_id, transition));
+                }
+            }
+        }
+        let genesis_id = self.genesis.id();
+
+        let mut outpoints = BTreeSet::new();
+        for (_, transition) in transitions.values() {
+            for input in &transition.inputs() {
+                let Opout { op, ty, no } = input.prev_out;
+                let seal = if op == genesis_id {
+                    self.genesis
+                        .assignments
+                        .get(&ty)
+                        .and_then(|assigns| assigns.revealed_seal_at(no).ok().flatten())
+                        .and_then(XChain::to_output_seal)
+                } else if let Some((witness_id, parent)) = transitions.get(&op) {
+                    parent
+                        .assignments
+                        .get(&ty)
+                        .and_then(|assigns| assigns.revealed_seal_at(no).ok().flatten())
+                        .and_then(|seal| seal.try_to_output_seal(*witness_id).ok())
+                } else {
+                    self.extensions
+                        .iter()
+                        .find(|extension| extension.id() == op)
+                        .and_then(|extension| extension.assignments.get(&ty))
+                        .and_then(|assigns| assigns.revealed_seal_at(no).ok().flatten())
+                        .and_then(XChain::to_output_seal)
+                };
+                outpoints.extend(seal.map(|seal| seal.to_outpoint()));
+            }
+        }
+        outpoints
+    }
+
+    /// Measures length of the longest chain of state transitions in the
+    /// consignment history. Genesis and state extensions have zero depth,
+    /// while each state transition is one level deeper than the deepest
+    /// operation it spends.
+    pub fn history_depth(&self) -> usize {
+        let transitions = self
+            .bundles
+            .iter()
+            .flat_map(|bw| bw.bundles())
+            .flat_map(|bundle| bundle.known_transitions.iter())
+            .map(|(opid, transition)| (*opid, transition))
+            .collect::<BTreeMap<_, _>>();
+
+        // Explicit stack is used instead of recursion, since the history may
+        // be deep enough to overflow the call stack.
+        let mut depths = BTreeMap::<OpId, usize>::new();
+        let mut max = 0;
+        for opid in transitions.keys() {
+            let mut stack = vec![*opid];
+            while let Some(opid) = stack.last().copied() {
+                if depths.contains_key(&opid) {
+                    stack.pop();
+                    continue;
+                }
+                let Some(transition) = transitions.get(&opid) else {
+                    depths.insert(opid, 0);
+                    stack.pop();
+                    continue;
+                };
+                let pending = transition
+                    .inputs
+                    .iter()
+                    .map(|input| input.prev_out.op)
+                    .filter(|prev| !depths.contains_key(prev))
+                    .collect::<Vec<_>>();
+                if pending.is_empty() {
+                    let depth = 1 + transition
+                        .inputs
+                        .iter()
+                        .map(|input| depths[&input.prev_out.op])
+                        .max()
+                        .unwrap_or_default();
+                    max = max.max(depth);
+                    depths.insert(opid, depth);
+                    stack.pop();
+                } else {
+                    stack.extend(pending);
+                }
+            }
+        }
+        max
+    }
+
+    /// Merges other consignment for the same contract into this one,
+    /// merge-revealing genesis, bundles and extensions and uniting terminals,
+    /// attachments, supplements and signatures.
+    ///
+    /// Schema, interfaces, types and scripts are taken from this consignment.
+    ///
+    /// # Errors
+    ///
+    /// If consignments belong to different contracts, their operations can't
+    /// be merge-revealed, or the resulting consignment exceeds size limits of
+    /// some of its collections.
+    pub fn merge(mut self, other: Self) -> Result<Self, ConsistencyError> {
+        let contract_id = self.contract_id();
+        if contract_id != other.contract_id() {
+            return Err(ConsistencyError::ContractMismatch(contract_id, other.contract_id()));
+        }
+
+        self.genesis = self.genesis.merge_reveal(other.genesis)?;
+
+        let mut terminals = self.terminals.into_inner();
+        for (bundle_id, terminal) in other.terminals {
+            match terminals.get_mut(&bundle_id) {
+                Some(prev) => {
+                    let mut seals = prev.seals.clone().into_inner();
+                    seals.extend(terminal.seals);
+                    prev.seals = confine("terminal seals", seals)?;
+                }
+                None => {
+                    terminals.insert(bundle_id, terminal);
+                }
+            }
+        }
+        self.terminals = confine("terminals", terminals)?;
+
+        let mut bundles = BTreeMap::<XWitnessId, BundledWitness>::new();
+        for bw in self.bundles.into_iter().chain(other.bundles) {
+            let witness_id = bw.witness_id();
+            let bw = match bundles.remove(&witness_id) {
+                Some(mut prev) => {
+                    prev.pub_witness = prev.pub_witness.merge_reveal(bw.pub_witness)?;
+                    prev.anchored_bundles = prev
+                        .anchored_bundles
+                        .merge_anchored(bw.anchored_bundles, witness_id)?;
+                    prev
+                }
+                None => bw,
+            };
+            bundles.insert(witness_id, bw);
+        }
+        self.bundles = confine("bundles", bundles.into_values().collect::<BTreeSet<_>>())?;
+
+        let mut extensions = BTreeMap::<OpId, Extension>::new();
+        for extension in self.extensions.into_iter().chain(other.extensions) {
+            let opid = extension.id();
+            let extension = match extensions.remove(&opid) {
+                Some(prev) => prev.merge_reveal(extension)?,
+                None => extension,
+            };
+            extensions.insert(opid, extension);
+        }
+        self.extensions = confine("extensions", extensions.into_values().collect::<BTreeSet<_>>())?;
+
+        let mut attachments = self.attachments.into_inner();
+        attachments.extend(other.attachments);
+        self.attachments = confine("attachments", attachments)?;
+
+        let mut supplements = self.supplements.into_inner();
+        supplements.extend(other.supplements);
+        self.supplements = confine("supplements", supplements)?;
+
+        let mut signatures = self.signatures.into_inner();
+        for (content_id, sigs) in other.signatures {
+            signatures.entry(content_id).or_insert(sigs);
+        }
+        self.signatures = confine("signatures", signatures)?;
+
+        Ok(self)
+    }
+
+    /// Replaces public witness of the bundles anchored to the `old` witness
+    /// with the `new` one, for instance after the witness transaction was
+    /// fee-bumped using RBF. The replacing transaction must contain the same
+    /// commitment, so the bundles and their anchors are kept unchanged.
+    ///
+    /// Returns `false` if the consignment has no bundles anchored to the
+    /// `old` witness.
+    ///
+    /// # Errors
+    ///
+    /// If the `new` witness doesn't provide the transaction, or if the
+    /// transaction doesn't commit to all the bundles anchored to the `old`
+    /// witness.
+    pub fn update_witness(
+        &mut self,
+        old: XWitnessId,
+        new: PubWitness,
+    ) -> Result<bool, ConsistencyError> {
+        let Some(bw) = self.bundles.iter().find(|bw| bw.witness_id() == old) else {
+            return Ok(false);
+        };
+        let Some(tx) = &new.tx else {
+            return Err(ConsistencyError::WitnessTxAbsent(new.txid));
+        };
+        let txid = tx.txid();
+        if txid != new.txid {
+            return Err(ConsistencyError::WitnessTxMismatch(new.txid, txid));
+        }
+        let contract_id = self.contract_id();
+        for (anchor, bundle) in bw.anchored_bundles.pairs() {
+            let bundle_id = bundle.bundle_id();
+            if anchor.verify(contract_id, bundle_id, tx).is_err() {
+                return Err(ConsistencyError::WitnessCommitmentMismatch(txid, bundle_id));
+            }
+        }
+        let pub_witness = bw.pub_witness.map_ref(|_| new.clone());
+        let new_id = pub_witness.to_witness_id();
+        if new_id != old && self.bundles.iter().any(|bw| bw.witness_id() == new_id) {
+            return Err(ConsistencyError::IncompatibleAnchors(new_id));
+        }
+        let bundles = self.bundles.iter().cloned().map(|mut bw| {
+            if bw.witness_id() == old {
+                bw.pub_witness = pub_witness.clone();
+            }
+            bw
+        });
+        self.bundles = LargeOrdSet::from_iter_unsafe(bundles);
+        Ok(true)
+    }
+
+    /// Lists terminal seals referencing bundles which are absent from the
+    /// consignment.
+    pub fn dangling_terminals(&self) -> Vec<TerminalDisclose> {
+        let bundle_ids = self
+            .bundles
+            .iter()
+            .flat_map(|bw| bw.bundles().map(TransitionBundle::bundle_id))
+            .collect::<BTreeSet<_>>();
+        self.terminals_disclose()
+            .filter(|terminal| !bundle_ids.contains(&terminal.bundle_id))
+            .collect()
+    }
+
+    /// Checks that history of all consignment operations is present in the
+    /// consignment and that all terminals reference known bundles.
+    ///
+    /// # Errors
+    ///
+    /// With the first operation missing from the consignment, or the first
+    /// bundle referenced by terminals which is absent from the consignment.
+    pub fn check_connectivity(&self) -> Result<(), ConsistencyError> {
+        if let Some(terminal) = self.dangling_terminals().first() {
+            return Err(ConsistencyError::DanglingTerminal(terminal.bundle_id));
+        }
+        let mut known = bset![self.genesis.id()];
+        let mut referenced = BTreeSet::new();
+        for bw in &self.bundles {
+            for bundle in bw.bundles() {
+                for (opid, transition) in &bundle.known_transitions {
+                    known.insert(*opid);
+                    referenced.extend(transition.inputs().iter().map(|input| input.prev_out.op));
+                }
+            }
+        }
+        for extension in &self.extensions {
+            known.insert(extension.id());
+            referenced.extend(extension.redeemed.values().copied());
+        }
+        match referenced.difference(&known).next() {
+            Some(opid) => Err(ConsistencyError::UnknownOperation(*opid)),
+            None => Ok(()),
+        }
+    }
+
+    /// Detects terminal seals which commit to a close method different from
+    /// the one used by the anchor of the terminal bundle.
+    pub fn seal_method_mismatches(&self) -> Vec<(BundleId, SealMethodMismatch)> {
+        let mut mismatches = vec![];
+        for (bundle_id, terminal) in &self.terminals {
+            let Some(method) = self
+                .bundles
+                .iter()
+                .find_map(|bw| bw.anchored_bundles.close_method(*bundle_id))
+            else {
+                continue;
+            };
+            for seal in &terminal.seals {
+                if let TerminalSeal::WitnessVout(seal) = seal.as_reduced_unsafe() {
+                    if let Err(mismatch) = seal.validate_method(method) {
+                        mismatches.push((*bundle_id, mismatch));
+                    }
+                }
+            }
+        }
+        mismatches
+    }
+
+    /// Detects state transitions which spend the same output of some previous
+    /// operation.
+    ///
+    /// Each returned item contains the spent owned state output and ids of two
+    /// state transitions consuming it. A valid consignment must not contain
+    /// such client-side double spends, so the returned vector is empty for any
+    /// valid consignment.
+    pub fn conflicting_spends(&self) -> Vec<ConflictingSpend> {
+        let mut spenders = BTreeMap::<Opout, BTreeSet<OpId>>::new();
+        for bundled_witness in &self.bundles {
+            for bundle in bundled_witness.anchored_bundles.bundles() {
+                for (opid, transition) in &bundle.known_transitions {
+                    for input in &transition.inputs {
+                        spenders.entry(input.prev_out).or_default().insert(*opid);
+                    }
+                }
+            }
+        }
+
+        let mut conflicts = vec![];
+        for (opout, ids) in spenders {
+            let ids = ids.into_iter().collect::<Vec<_>>();
+            for (pos, first) in ids.iter().enumerate() {
+                for second in &ids[pos + 1..] {
+                    conflicts.push(ConflictingSpend {
+                        opout,
+                        first: *first,
+                        second: *second,
+                    });
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// Computes contract state produced by the history leading to the `tips`
+    /// operations: all operations which are the tips or their ancestors are
+    /// applied starting from genesis, and the state spent by them is excluded.
+    ///
+    /// Witness transactions are not resolved, so all global state is ordered
+    /// as if the witnesses were not mined. State extensions are included only
+    /// if they are spent by some of the applied state transitions, which
+    /// provide their witness.
+    ///
+    /// # Errors
+    ///
+    /// [`ConsistencyError::UnknownOperation`] if some of the tips or their
+    /// ancestors are absent from the consignment.
+    pub fn state_at(&self, tips: &BTreeSet<OpId>) -> Result<ContractState, ConsistencyError> {
+        let mut transitions = BTreeMap::new();
+        for bw in &self.bundles {
+            let witness_id = bw.witness_id();
+            for bundle in bw.bundles() {
+                for (opid, transition) in &bundle.known_transitions {
+                    transitions.insert(*opid, (witness_id, transition));
+                }
+            }
+        }
+        let extensions = self
+            .extensions
+            .iter()
+            .map(|extension| (extension.id(), extension))
+            .collect::<BTreeMap<_, _>>();
+        let genesis_id = self.genesis.id();
+
+        let mut ancestors = BTreeSet::new();
+        let mut queue = tips.iter().copied().collect::<Vec<_>>();
+        while let Some(opid) = queue.pop() {
+            if opid == genesis_id || !ancestors.insert(opid) {
+                continue;
+            }
+            if let Some((_, transition)) = transitions.get(&opid) {
+                queue.extend(transition.inputs.iter().map(|input| input.prev_out.op));
+            } else if let Some(extension) = extensions.get(&opid) {
+                queue.extend(extension.redeemed.values().copied());
+            } else {
+                return Err(ConsistencyError::UnknownOperation(opid));
+            }
+        }
+
+        let mut spent = BTreeMap::<OpId, Vec<Opout>>::new();
+        let mut extension_witness = BTreeMap::<OpId, XWitnessId>::new();
+        for (opid, (witness_id, transition)) in &transitions {
+            if !ancestors.contains(opid) {
+                continue;
+            }
+            for input in &transition.inputs {
+                let prev_out = input.prev_out;
+                spent.entry(prev_out.op).or_default().push(prev_out);
+                if extensions.contains_key(&prev_out.op) {
+                    extension_witness
+                        .entry(prev_out.op)
+                        .and_modify(|id| *id = (*id).min(*witness_id))
+                        .or_insert(*witness_id);
+                }
+            }
+        }
+
+        let mut genesis = self.genesis.clone();
+        conceal_spent(&mut genesis.assignments, spent.get(&genesis_id));
+        let mut history = ContractHistory::with(self.schema_id(), self.contract_id(), &genesis);
+        for (opid, (witness_id, transition)) in transitions {
+            if ancestors.contains(&opid) {
+                let mut transition = transition.clone();
+                conceal_spent(&mut transition.assignments, spent.get(&opid));
+                history.add_transition(&transition, WitnessAnchor::from_mempool(witness_id));
+            }
+        }
+        for (opid, witness_id) in extension_witness {
+            if ancestors.contains(&opid) {
+                let mut extension = extensions[&opid].clone();
+                conceal_spent(&mut extension.assignments, spent.get(&opid));
+                history.add_extension(&extension, WitnessAnchor::from_mempool(witness_id));
+            }
+        }
+
+        Ok(ContractState {
+            schema: self.schema.clone(),
+            history,
+        })
+    }
+
     pub fn update_history<R: ResolveHeight>(
         &self,
         history: Option<ContractHistory>,
@@ -347,26 +1108,54 @@ impl<const TRANSFER: bool> Consignment<TRANSFER> {
         //_: &impl SigValidator,
         testnet: bool,
     ) -> Result<ValidConsignment<TRANSFER>, (validation::Status, Consignment<TRANSFER>)> {
-        let index = IndexedConsignment::new(&self);
-        let mut status = Validator::validate(&index, resolver, testnet);
-
-        let validity = status.validity();
+        self.validate_with_policy(resolver, testnet, &ValidationPolicy::default())
+            .map_err(|(status, _, consignment)| (status, consignment))
+    }
 
-        if self.transfer != TRANSFER {
-            status.add_warning(Warning::Custom(s!("invalid consignment type")));
-        }
-        // TODO: check that interface ids match implementations
-        // TODO: check bundle ids listed in terminals are present in the consignment
-        // TODO: check attach ids from data containers are present in operations
-        // TODO: validate sigs and remove untrusted
+    /// Validates the consignment, using witness transactions from `cache`
+    /// (which must be previously verified by the caller) and resolving only
+    /// the witnesses which are absent from the cache.
+    #[allow(clippy::result_large_err)]
+    pub fn validate_with_witnesses(
+        self,
+        resolver: &impl ResolveWitness,
+        cache: &BTreeMap<XWitnessId, XWitnessTx>,
+        testnet: bool,
+    ) -> Result<ValidConsignment<TRANSFER>, (validation::Status, Consignment<TRANSFER>)> {
+        self.validate(&mut CachedResolver::new(cache, resolver), testnet)
+    }
+}
 
-        if validity != Validity::Valid {
-            Err((status, self))
-        } else {
-            Ok(ValidConsignment {
-                validation_status: status,
-                consignment: self,
-            })
+/// Conceals `spent` assignments, so they are not added to the contract state,
+/// keeping the numbering of the remaining assignments intact.
+pub(super) fn conceal_spent<Seal: ExposedSeal>(
+    assignments: &mut Assignments<Seal>,
+    spent: Option<&Vec<Opout>>,
+) {
+    for opout in spent.into_iter().flatten() {
+        let no = opout.no as usize;
+        match assignments.get_mut(&opout.ty) {
+            Some(TypedAssigns::Declarative(list)) => {
+                if let Some(assign) = list.get_mut(no) {
+                    *assign = assign.conceal();
+                }
+            }
+            Some(TypedAssigns::Fungible(list)) => {
+                if let Some(assign) = list.get_mut(no) {
+                    *assign = assign.conceal();
+                }
+            }
+            Some(TypedAssigns::Structured(list)) => {
+                if let Some(assign) = list.get_mut(no) {
+                    *assign = assign.conceal();
+                }
+            }
+            Some(TypedAssigns::Attachment(list)) => {
+                if let Some(assign) = list.get_mut(no) {
+                    *assign = assign.conceal();
+                }
+            }
+            None => {}
         }
     }
 }
@@ -408,12 +1197,805 @@ impl<const TRANSFER: bool> StrictArmor for Consignment<TRANSFER> {
 
 impl<const TRANSFER: bool> FromStr for Consignment<TRANSFER> {
     type Err = armor::StrictArmorError;
-    fn from_str(s: &str) -> Result<Self, Self::Err> { Self::from_ascii_armored_str(s) }
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Armored text pasted from emails or chats may get indented or padded
+        let s = s
+            .trim()
+            .lines()
+            .map(str::trim)
+            .collect::<Vec<_>>()
+            .join("\n");
+        Self::from_ascii_armored_str(&s)
+    }
 }
 
 #[cfg(test)]
-mod test {
+pub(crate) mod test {
+    use std::cell::RefCell;
+
+    use amplify::confinement::SmallVec;
+    use amplify::hex::{FromHex, ToHex};
+    use bp::dbc::opret::OpretProof;
+    use bp::dbc::tapret::{TapretPathProof, TapretProof};
+    use bp::dbc::Anchor;
+    use bp::{LockTime, Outpoint, Sats, ScriptPubkey, Tx, TxOut};
+    use commit_verify::{mpc, CommitId, TryCommitVerify};
+    use rgb::validation::{Failure, WitnessResolverError};
+    use rgb::{
+        AssetTag, Assign, AssignmentType, Assignments, GenesisSeal, Input, InputMap, Opout,
+        Redeemed, RevealedValue, Transition, TransitionType, TypedAssigns, Valencies, VoidState,
+    };
+    use strict_encoding::{StrictEncode, StrictWriter};
+
     use super::*;
+    use crate::containers::VoutSeal;
+
+    pub(crate) struct NoResolver;
+    impl ResolveWitness for NoResolver {
+        fn resolve_pub_witness(
+            &self,
+            witness_id: XWitnessId,
+        ) -> Result<XWitnessTx, WitnessResolverError> {
+            Err(WitnessResolverError::Unknown(witness_id))
+        }
+    }
+
+    pub(crate) fn transition(ty: u16, inputs: impl IntoIterator<Item = Opout>) -> Transition {
+        let mut transition = Transition::strict_dumb();
+        transition.transition_type = TransitionType::with(ty);
+        transition.inputs = SmallOrdSet::try_from_iter(inputs.into_iter().map(Input::with))
+            .unwrap()
+            .into();
+        transition
+    }
+
+    pub(crate) fn with_seals(
+        mut transition: Transition,
+        seals: impl IntoIterator<Item = GraphSeal>,
+    ) -> Transition {
+        transition.assignments =
+            Assignments::from_inner(
+                Confined::try_from(BTreeMap::from([(
+                    AssignmentType::with(1),
+                    TypedAssigns::Declarative(
+                        SmallVec::try_from_iter(seals.into_iter().map(|seal| {
+                            Assign::revealed(XChain::Bitcoin(seal), VoidState::default())
+                        }))
+                        .unwrap(),
+                    ),
+                )]))
+                .unwrap(),
+            );
+        transition
+    }
+
+    pub(crate) fn bundled_witness(
+        txid: u8,
+        transitions: impl IntoIterator<Item = Transition>,
+    ) -> BundledWitness {
+        let known_transitions = transitions
+            .into_iter()
+            .map(|transition| (transition.id(), transition))
+            .collect::<BTreeMap<_, _>>();
+        let input_map = known_transitions
+            .keys()
+            .enumerate()
+            .map(|(vin, opid)| (vin as u32, *opid))
+            .collect::<BTreeMap<_, _>>();
+        let bundle = TransitionBundle {
+            close_method: CloseMethod::OpretFirst,
+            input_map: InputMap::from(
+                Confined::try_from_iter(
+                    input_map.into_iter().map(|(vin, opid)| (vin.into(), opid)),
+                )
+                .unwrap(),
+            ),
+            known_transitions: Confined::try_from(known_transitions).unwrap(),
+        };
+        BundledWitness {
+            pub_witness: XChain::Bitcoin(PubWitness::new(Txid::from_byte_array([txid; 32]))),
+            anchored_bundles: AnchoredBundles::Opret(strict_dumb!(), bundle),
+        }
+    }
+
+    /// Sample transfer consignment shared by the tests.
+    pub(crate) fn armored_transfer() -> Transfer {
+        Transfer::from_str(include_str!("../../asset/armored_transfer.default"))
+            .expect("transfer from str should work")
+    }
+
+    pub(crate) fn bundle_id(witness: &BundledWitness) -> BundleId {
+        witness.bundles().next().unwrap().bundle_id()
+    }
+
+    pub(crate) fn bundle_ids(transfer: &Transfer) -> BTreeSet<BundleId> {
+        transfer
+            .bundles
+            .iter()
+            .flat_map(|bw| bw.bundles().map(TransitionBundle::bundle_id))
+            .collect()
+    }
+
+    pub(crate) fn transfer(bundles: impl IntoIterator<Item = BundledWitness>) -> Transfer {
+        let mut transfer = Transfer::strict_dumb();
+        transfer.transfer = true;
+        transfer.bundles = LargeOrdSet::try_from_iter(bundles).unwrap();
+        transfer
+    }
+
+    #[test]
+    fn genesis_schema_mismatch() {
+        let mut transfer = armored_transfer();
+        let schema_id = transfer.schema_id();
+        let genesis_schema_id = SchemaId::from_byte_array([0xEE; 32]);
+        transfer.genesis.schema_id = genesis_schema_id;
+
+        let (status, _) = transfer.validate(&mut NoResolver, false).unwrap_err();
+        assert!(status.failures.contains(&Failure::SchemaMismatch {
+            expected: genesis_schema_id,
+            actual: schema_id,
+        }));
+    }
+
+    #[test]
+    fn armored_whitespace_tolerance() {
+        let armored = include_str!("../../asset/armored_transfer.default");
+        let transfer = Transfer::from_str(armored).unwrap();
+        let pasted = armored
+            .lines()
+            .map(|line| format!("    {line}  "))
+            .collect::<Vec<_>>()
+            .join("\r\n");
+        assert_eq!(
+            Transfer::from_str(&format!("\n\n{pasted}\n"))
+                .unwrap()
+                .consignment_id(),
+            transfer.consignment_id()
+        );
+    }
+
+    #[test]
+    fn consignment_id_bytes() {
+        let id = ConsignmentId::from_str(
+            "rgb:csg:poAMvm9j-NdapxqA-MJ!5dwP-d!IIt2A-T!5OiXE-Tl54Yew#guide-campus-arctic",
+        )
+        .unwrap();
+        assert_eq!(ConsignmentId::from_array(*id.as_bytes()), id);
+        assert_eq!(ConsignmentId::from(*id.as_bytes()), id);
+        assert_eq!(ConsignmentId::from_hex(&id.to_hex()).unwrap(), id);
+    }
+
+    #[test]
+    fn consignment_id_null() {
+        assert!(ConsignmentId::ZERO.is_null());
+        assert!(ConsignmentId::from_array([0u8; 32]).is_null());
+
+        let armored = armored_transfer();
+        assert!(!armored.consignment_id().is_null());
+    }
+
+    #[test]
+    fn fingerprint() {
+        let id = ConsignmentId::from_str(
+            "rgb:csg:poAMvm9j-NdapxqA-MJ!5dwP-d!IIt2A-T!5OiXE-Tl54Yew#guide-campus-arctic",
+        )
+        .unwrap();
+        assert_eq!(id.fingerprint(), "poAMvm9j..Tl54Yew");
+        assert_eq!(id.fingerprint(), id.fingerprint());
+        assert_ne!(id.fingerprint(), ConsignmentId::from_array([0xAA; 32]).fingerprint());
+
+        let armored = armored_transfer();
+        assert_eq!(armored.fingerprint(), armored.consignment_id().fingerprint());
+    }
+
+    #[test]
+    fn matches_schema() {
+        let transfer = armored_transfer();
+        let schema_id = SchemaId::from_str(
+            "rgb:sch:CyqM42yAdM1moWyNZPQedAYt73BM$k9z$dKLUXY1voA#cello-global-deluxe",
+        )
+        .unwrap();
+        assert_eq!(transfer.schema_id(), schema_id);
+        assert!(transfer.matches_schema(schema_id));
+        assert!(!transfer.matches_schema(SchemaId::from_byte_array([0xAA; 32])));
+    }
+
+    #[test]
+    fn merge_anchored() {
+        let genesis_id = OpId::from_byte_array([0xAA; 32]);
+        let ty = AssignmentType::with(1);
+        let opret = bundled_witness(1, [transition(1, [Opout::new(genesis_id, ty, 0)])]);
+        let AnchoredBundles::Opret(_, bundle) =
+            bundled_witness(1, [transition(2, [Opout::new(genesis_id, ty, 1)])]).anchored_bundles
+        else {
+            unreachable!()
+        };
+        let mut tapret = opret.clone();
+        tapret.anchored_bundles = AnchoredBundles::Tapret(strict_dumb!(), bundle.clone());
+        let other = bundled_witness(2, [transition(3, [Opout::new(genesis_id, ty, 2)])]);
+
+        let merged = transfer([opret.clone()])
+            .merge(transfer([opret.clone(), other]))
+            .and_then(|merged| merged.merge(transfer([tapret.clone()])))
+            .unwrap();
+        assert_eq!(merged.bundles.len(), 2);
+        let combined = merged.bundles.first().unwrap();
+        assert_eq!(combined.witness_id(), opret.witness_id());
+        assert!(matches!(
+            &combined.anchored_bundles,
+            AnchoredBundles::Double { tapret_bundle, opret_bundle, .. }
+                if tapret_bundle == &bundle &&
+                    Some(opret_bundle) == opret.anchored_bundles.bundles().next()
+        ));
+
+        let remerged = merged.clone().merge(transfer([opret])).unwrap();
+        assert_eq!(remerged.bundles.first().unwrap().anchored_bundles, combined.anchored_bundles);
+
+        let mut anchor = Anchor::<mpc::MerkleProof, TapretProof>::strict_dumb();
+        anchor.dbc_proof.path_proof = TapretPathProof::root(1);
+        let mut conflicting = tapret.clone();
+        conflicting.anchored_bundles = AnchoredBundles::Tapret(anchor, bundle);
+        assert_eq!(
+            transfer([tapret.clone()])
+                .merge(transfer([conflicting]))
+                .unwrap_err(),
+            ConsistencyError::IncompatibleAnchors(tapret.witness_id())
+        );
+    }
+
+    #[test]
+    fn merge_reveal_double_with_opret() {
+        let genesis_id = OpId::from_byte_array([0xAA; 32]);
+        let ty = AssignmentType::with(1);
+        let seal = GraphSeal::with_blinded_vout(CloseMethod::OpretFirst, 0u32, 1);
+        let revealed = with_seals(transition(1, [Opout::new(genesis_id, ty, 0)]), [seal]);
+        let mut concealed = revealed.clone();
+        let Some(TypedAssigns::Declarative(assigns)) = concealed.assignments.get_mut(&ty) else {
+            unreachable!()
+        };
+        let assign = assigns.get_mut(0).unwrap();
+        *assign = assign.conceal();
+        assert_eq!(concealed.id(), revealed.id());
+
+        let bundle = |transition: Transition| {
+            bundled_witness(1, [transition])
+                .anchored_bundles
+                .bundles()
+                .next()
+                .unwrap()
+                .clone()
+        };
+        let tapret_bundle = bundle(transition(2, [Opout::new(genesis_id, ty, 1)]));
+        let double = AnchoredBundles::Double {
+            tapret_anchor: strict_dumb!(),
+            tapret_bundle: tapret_bundle.clone(),
+            opret_anchor: strict_dumb!(),
+            opret_bundle: bundle(concealed),
+        };
+        let opret = AnchoredBundles::Opret(strict_dumb!(), bundle(revealed.clone()));
+        let expected = AnchoredBundles::Double {
+            tapret_anchor: strict_dumb!(),
+            tapret_bundle,
+            opret_anchor: strict_dumb!(),
+            opret_bundle: bundle(revealed),
+        };
+        assert_eq!(double.clone().merge_reveal(opret.clone()).unwrap(), expected);
+        assert_eq!(opret.merge_reveal(double).unwrap(), expected);
+    }
+
+    #[test]
+    fn merge_too_large() {
+        let terminals = |range: std::ops::Range<u32>| {
+            let seal = XChain::Bitcoin(TerminalSeal::from(VoutSeal::with_opret(0u32, 0)));
+            SmallOrdMap::try_from_iter(range.map(|no| {
+                let mut id = [0u8; 32];
+                id[..4].copy_from_slice(&no.to_le_bytes());
+                (BundleId::from_byte_array(id), Terminal::new(seal))
+            }))
+            .unwrap()
+        };
+        let mut first = transfer([]);
+        first.terminals = terminals(0..40_000);
+        let mut second = transfer([]);
+        second.terminals = terminals(30_000..70_000);
+
+        assert_eq!(first.clone().merge(transfer([])).unwrap().terminals.len(), 40_000);
+        assert_eq!(first.merge(second).unwrap_err(), ConsistencyError::TooLarge {
+            kind: "terminals",
+            limit: u16::MAX as usize,
+            actual: 70_000
+        });
+    }
+
+    /// Builds witness transaction committing to the `bundle` with opret and
+    /// the anchor for it. Transactions with different `lock_time` commit to
+    /// the same bundle, as it happens with RBF.
+    fn opret_commit(
+        contract_id: ContractId,
+        bundle: &TransitionBundle,
+        lock_time: u32,
+    ) -> (Tx, Anchor<mpc::MerkleProof, OpretProof>) {
+        let protocol_id = mpc::ProtocolId::from(contract_id);
+        let source = mpc::MultiSource {
+            messages: Confined::try_from(bmap! {
+                protocol_id => mpc::Message::from(bundle.bundle_id())
+            })
+            .unwrap(),
+            static_entropy: Some(0),
+            ..default!()
+        };
+        let tree = mpc::MerkleTree::try_commit(&source).unwrap();
+        let commitment = tree.commit_id();
+        let mut tx = Tx::strict_dumb();
+        tx.lock_time = LockTime::from_consensus_u32(lock_time);
+        tx.outputs = Confined::try_from(vec![TxOut::new(
+            ScriptPubkey::op_return(&commitment.to_byte_array()),
+            Sats::ZERO,
+        )])
+        .unwrap();
+        let proof = mpc::MerkleBlock::from(tree)
+            .to_merkle_proof(protocol_id)
+            .unwrap();
+        (tx, Anchor::new(proof, OpretProof::default()))
+    }
+
+    #[test]
+    fn update_witness() {
+        let genesis_id = OpId::from_byte_array([0xAA; 32]);
+        let opout = Opout::new(genesis_id, AssignmentType::with(1), 0);
+        let contract_id = Transfer::strict_dumb().contract_id();
+        let mut witness = bundled_witness(1, [transition(1, [opout])]);
+        let bundle = witness.anchored_bundles.bundles().next().unwrap().clone();
+        let (tx, anchor) = opret_commit(contract_id, &bundle, 1);
+        witness.pub_witness = XChain::Bitcoin(PubWitness::with(tx));
+        witness.anchored_bundles = AnchoredBundles::Opret(anchor, bundle.clone());
+        let old = witness.witness_id();
+
+        let (conflict, _) = opret_commit(contract_id, &bundle, 2);
+        let mut other = bundled_witness(2, [transition(2, [opout])]);
+        other.pub_witness = XChain::Bitcoin(PubWitness::with(conflict.clone()));
+        let unrelated = bundled_witness(3, [transition(3, [opout])]);
+        let unrelated_bundle = unrelated.anchored_bundles.bundles().next().unwrap();
+
+        let mut transfer = transfer([witness, other.clone()]);
+        let before = bundle_ids(&transfer);
+
+        let (replacement, _) = opret_commit(contract_id, &bundle, 3);
+        let txid = replacement.txid();
+        assert!(!transfer
+            .update_witness(XChain::Bitcoin(Txid::from_byte_array([4; 32])), PubWitness::new(txid))
+            .unwrap());
+        assert_eq!(
+            transfer.update_witness(old, PubWitness::new(txid)),
+            Err(ConsistencyError::WitnessTxAbsent(txid))
+        );
+        let mut mismatch = PubWitness::with(replacement.clone());
+        mismatch.txid = Txid::from_byte_array([5; 32]);
+        assert_eq!(
+            transfer.update_witness(old, mismatch),
+            Err(ConsistencyError::WitnessTxMismatch(Txid::from_byte_array([5; 32]), txid))
+        );
+        let (mismatch, _) = opret_commit(contract_id, unrelated_bundle, 3);
+        assert_eq!(
+            transfer.update_witness(old, PubWitness::with(mismatch.clone())),
+            Err(ConsistencyError::WitnessCommitmentMismatch(mismatch.txid(), bundle.bundle_id()))
+        );
+        assert_eq!(
+            transfer.update_witness(old, PubWitness::with(conflict.clone())),
+            Err(ConsistencyError::IncompatibleAnchors(other.witness_id()))
+        );
+
+        assert!(transfer
+            .update_witness(old, PubWitness::with(replacement))
+            .unwrap());
+        assert_eq!(
+            transfer
+                .bundles
+                .iter()
+                .map(BundledWitness::witness_id)
+                .collect::<BTreeSet<_>>(),
+            bset![other.witness_id(), XChain::Bitcoin(txid)]
+        );
+        assert_eq!(bundle_ids(&transfer), before);
+    }
+
+    #[test]
+    fn terminal_transitions_by_assignment_type() {
+        let seal = GraphSeal::with_blinded_vout(CloseMethod::OpretFirst, 0u32, 1);
+        let genesis_id = OpId::from_byte_array([0xAA; 32]);
+        let ty = AssignmentType::with(1);
+        let assigning = with_seals(transition(1, [Opout::new(genesis_id, ty, 0)]), [seal]);
+        let plain = transition(2, [Opout::new(genesis_id, ty, 1)]);
+        let hidden = with_seals(transition(3, [Opout::new(genesis_id, ty, 2)]), [seal]);
+        let terminal = bundled_witness(1, [assigning.clone(), plain]);
+        let bundle_id = bundle_id(&terminal);
+
+        let mut history = transfer([terminal, bundled_witness(2, [hidden])]);
+        history.terminals = SmallOrdMap::try_from_iter([(bundle_id, Terminal {
+            seals: SmallOrdSet::try_from_iter([XChain::Bitcoin(TerminalSeal::conceal_from(seal))])
+                .unwrap(),
+        })])
+        .unwrap();
+
+        assert_eq!(history.terminal_transitions_by_assignment_type(ty), vec![&assigning]);
+        assert!(history
+            .terminal_transitions_by_assignment_type(AssignmentType::with(2))
+            .is_empty());
+    }
+
+    #[test]
+    fn state_at() {
+        let ty = AssignmentType::with(1);
+        let fungible = |value| RevealedValue::new_random_blinding(value, AssetTag::strict_dumb());
+        let mut history = transfer([]);
+        let issue = GenesisSeal::with_blinding(
+            CloseMethod::OpretFirst,
+            Txid::from_byte_array([0xEE; 32]),
+            0u32,
+            1,
+        );
+        history.genesis.assignments = Assignments::from_inner(
+            Confined::try_from(BTreeMap::from([(
+                ty,
+                TypedAssigns::Fungible(
+                    SmallVec::try_from_iter([Assign::revealed(
+                        XChain::Bitcoin(issue),
+                        fungible(100),
+                    )])
+                    .unwrap(),
+                ),
+            )]))
+            .unwrap(),
+        );
+        let genesis_id = history.genesis.id();
+
+        let mut spend = transition(1, [Opout::new(genesis_id, ty, 0)]);
+        spend.assignments = Assignments::from_inner(
+            Confined::try_from(BTreeMap::from([(
+                ty,
+                TypedAssigns::Fungible(
+                    SmallVec::try_from_iter([(0u32, 60), (1, 40)].map(|(vout, value)| {
+                        Assign::revealed(
+                            XChain::Bitcoin(VoutSeal::with_opret(vout, 0).into()),
+                            fungible(value),
+                        )
+                    }))
+                    .unwrap(),
+                ),
+            )]))
+            .unwrap(),
+        );
+        let spend_id = spend.id();
+        history.bundles = LargeOrdSet::try_from_iter([bundled_witness(1, [spend])]).unwrap();
+
+        let balance = |state: &ContractState| {
+            state
+                .fungibles()
+                .iter()
+                .map(|assignment| (assignment.opout.op, assignment.state.value.as_u64()))
+                .collect::<Vec<_>>()
+        };
+        let issued = history.state_at(&bset![genesis_id]).unwrap();
+        assert_eq!(balance(&issued), vec![(genesis_id, 100)]);
+        let state = history.state_at(&bset![spend_id]).unwrap();
+        let mut found = balance(&state);
+        found.sort_by_key(|(_, value)| *value);
+        assert_eq!(found, vec![(spend_id, 40), (spend_id, 60)]);
+        assert_eq!(found.iter().map(|(_, value)| value).sum::<u64>(), 100);
+
+        let unknown = OpId::from_byte_array([0x11; 32]);
+        assert_eq!(
+            history.state_at(&bset![unknown]).unwrap_err(),
+            ConsistencyError::UnknownOperation(unknown)
+        );
+    }
+
+    #[test]
+    fn open_valencies() {
+        let mut history = transfer([]);
+        let [first, second] = [1, 2].map(ValencyType::with);
+        history.genesis.valencies =
+            Valencies::from_inner(TinyOrdSet::try_from_iter([first, second]).unwrap());
+        let genesis_id = history.genesis.id();
+        assert_eq!(history.open_valencies(), bset![(genesis_id, first), (genesis_id, second)]);
+
+        let mut extension = Extension::strict_dumb();
+        extension.redeemed =
+            Redeemed::from_inner(TinyOrdMap::try_from_iter([(first, genesis_id)]).unwrap());
+        extension.valencies = Valencies::from_inner(TinyOrdSet::try_from_iter([first]).unwrap());
+        let extension_id = extension.id();
+        history.extensions = LargeOrdSet::try_from_iter([extension]).unwrap();
+        assert_eq!(history.open_valencies(), bset![(genesis_id, second), (extension_id, first)]);
+    }
+
+    #[test]
+    fn spent_outpoints() {
+        let witness_seal = GraphSeal::with_blinded_vout(CloseMethod::OpretFirst, 0u32, 1);
+        let explicit_txid = Txid::from_byte_array([9; 32]);
+        let explicit_seal =
+            GraphSeal::with_blinding(CloseMethod::OpretFirst, explicit_txid, 3u32, 2);
+        let genesis_id = OpId::from_byte_array([0xAA; 32]);
+        let unknown_id = OpId::from_byte_array([0xBB; 32]);
+        let ty = AssignmentType::with(1);
+        let parent = with_seals(transition(1, [Opout::new(genesis_id, ty, 0)]), [
+            witness_seal,
+            explicit_seal,
+        ]);
+        let child = transition(2, [
+            Opout::new(parent.id(), ty, 0),
+            Opout::new(parent.id(), ty, 1),
+            Opout::new(unknown_id, ty, 0),
+        ]);
+
+        let history = transfer([bundled_witness(1, [parent]), bundled_witness(2, [child])]);
+        assert_eq!(history.spent_outpoints(), bset![
+            XChain::Bitcoin(Outpoint::new(Txid::from_byte_array([1; 32]), 0u32)).into(),
+            XChain::Bitcoin(Outpoint::new(explicit_txid, 3u32)).into(),
+        ]);
+    }
+
+    #[test]
+    fn terminal_seals() {
+        let armored = armored_transfer();
+        assert_eq!(armored.terminal_seals().count(), 0);
+
+        let vout = XChain::Bitcoin(TerminalSeal::from(VoutSeal::with_opret(0u32, 1)));
+        let concealed = XChain::Bitcoin(TerminalSeal::from(
+            GraphSeal::with_blinding(
+                CloseMethod::OpretFirst,
+                Txid::from_byte_array([9; 32]),
+                3u32,
+                2,
+            )
+            .conceal(),
+        ));
+        let mut history = transfer([]);
+        history.terminals = SmallOrdMap::try_from_iter([
+            (BundleId::from_byte_array([1; 32]), Terminal {
+                seals: SmallOrdSet::try_from_iter([vout, concealed]).unwrap(),
+            }),
+            (BundleId::from_byte_array([2; 32]), Terminal {
+                seals: SmallOrdSet::try_from_iter([vout]).unwrap(),
+            }),
+        ])
+        .unwrap();
+
+        let seals = history.terminal_seals().collect::<Vec<_>>();
+        assert_eq!(seals.len(), 3);
+        assert_eq!(
+            seals
+                .iter()
+                .filter(|seal| matches!(seal, XChain::Bitcoin(TerminalSeal::WitnessVout(_))))
+                .count(),
+            2
+        );
+        assert_eq!(
+            seals
+                .iter()
+                .filter(|seal| matches!(seal, XChain::Bitcoin(TerminalSeal::ConcealedUtxo(_))))
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn partition_by_close_method() {
+        let ty = AssignmentType::with(1);
+        let genesis_id = Transfer::strict_dumb().genesis.id();
+        let opret = bundled_witness(1, [transition(1, [Opout::new(genesis_id, ty, 0)])]);
+        let mut tapret = bundled_witness(2, [transition(2, [Opout::new(genesis_id, ty, 1)])]);
+        let AnchoredBundles::Opret(_, bundle) = tapret.anchored_bundles else {
+            unreachable!()
+        };
+        tapret.anchored_bundles = AnchoredBundles::Tapret(strict_dumb!(), bundle);
+        let seal = XChain::Bitcoin(TerminalSeal::from(VoutSeal::with_opret(0u32, 1)));
+
+        let mut history = transfer([opret.clone(), tapret.clone()]);
+        history.terminals =
+            SmallOrdMap::try_from_iter([bundle_id(&opret), bundle_id(&tapret)].map(|bundle_id| {
+                (bundle_id, Terminal {
+                    seals: SmallOrdSet::try_from_iter([seal]).unwrap(),
+                })
+            }))
+            .unwrap();
+
+        let partitions = history.partition_by_close_method();
+        assert_eq!(partitions.keys().copied().collect::<Vec<_>>(), vec![
+            Some(CloseMethod::OpretFirst),
+            Some(CloseMethod::TapretFirst)
+        ]);
+        for (method, witness) in
+            [(CloseMethod::OpretFirst, &opret), (CloseMethod::TapretFirst, &tapret)]
+        {
+            let partition = &partitions[&Some(method)];
+            assert_eq!(partition.genesis, history.genesis);
+            assert_eq!(partition.bundles.iter().collect::<Vec<_>>(), vec![witness]);
+            assert_eq!(partition.terminals.keys().collect::<Vec<_>>(), vec![&bundle_id(witness)]);
+            partition.check_connectivity().unwrap();
+        }
+    }
+
+    #[test]
+    fn anchor_commitment_sizes() {
+        let genesis_id = OpId::from_byte_array([0xAA; 32]);
+        let ty = AssignmentType::with(1);
+        let single = bundled_witness(1, [transition(1, [Opout::new(genesis_id, ty, 0)])]);
+        let double = bundled_witness(2, [
+            transition(2, [Opout::new(genesis_id, ty, 1)]),
+            transition(3, [Opout::new(genesis_id, ty, 2)]),
+        ]);
+        let len = |bw: &BundledWitness| {
+            bw.bundles()
+                .map(|bundle| {
+                    bundle
+                        .strict_encode(StrictWriter::in_memory::<{ usize::MAX }>())
+                        .unwrap()
+                        .unbox()
+                        .unconfine()
+                        .len()
+                })
+                .sum::<usize>()
+        };
+
+        let history = transfer([single.clone(), double.clone()]);
+        assert_eq!(history.anchor_commitment_sizes(), bmap! {
+            single.witness_id() => len(&single),
+            double.witness_id() => len(&double),
+        });
+    }
+
+    #[test]
+    fn compose() {
+        let ty = AssignmentType::with(1);
+        let seal = GraphSeal::with_blinded_vout(CloseMethod::OpretFirst, 0u32, 1);
+        let dumb = Transfer::strict_dumb();
+        let genesis_id = dumb.genesis.id();
+        let first = transition(1, [Opout::new(genesis_id, ty, 0)]);
+        let second = with_seals(transition(2, [Opout::new(first.id(), ty, 0)]), [seal]);
+        let unrelated = transition(3, [Opout::new(genesis_id, ty, 1)]);
+        let (first_id, second_id) = (first.id(), second.id());
+        let store = [
+            bundled_witness(1, [first]),
+            bundled_witness(2, [second]),
+            bundled_witness(3, [unrelated]),
+        ];
+        let resolve = |opid: OpId| {
+            store
+                .iter()
+                .find(|bw| {
+                    bw.bundles()
+                        .any(|bundle| bundle.known_transitions.contains_key(&opid))
+                })
+                .cloned()
+        };
+
+        let terminal = XChain::Bitcoin(TerminalSeal::conceal_from(seal));
+        let composed = Transfer::compose(
+            dumb.schema.clone(),
+            dumb.genesis.clone(),
+            &[(second_id, terminal)],
+            resolve,
+        )
+        .unwrap();
+
+        let mut expected = transfer(store[..2].iter().cloned());
+        expected.terminals =
+            SmallOrdMap::try_from_iter([(bundle_id(&store[1]), Terminal::new(terminal))]).unwrap();
+        assert_eq!(composed, expected);
+
+        let other = XChain::Bitcoin(TerminalSeal::from(VoutSeal::with_opret(1u32, 2)));
+        assert_eq!(
+            Transfer::compose(
+                dumb.schema.clone(),
+                dumb.genesis.clone(),
+                &[(second_id, other)],
+                resolve
+            )
+            .unwrap_err(),
+            ConsistencyError::UnrelatedSeals
+        );
+        assert_eq!(
+            Transfer::compose(dumb.schema, dumb.genesis, &[(second_id, terminal)], |opid| {
+                (opid == second_id).then(|| store[1].clone())
+            })
+            .unwrap_err(),
+            ConsistencyError::UnknownOperation(first_id)
+        );
+    }
+
+    #[test]
+    fn total_by_type() {
+        let ty = AssignmentType::with(1);
+        let genesis_id = OpId::from_byte_array([0xAA; 32]);
+        let seals = [0u32, 1, 2].map(|vout| VoutSeal::with_opret(vout, vout as u64));
+        let fungible = |values: [u64; 3]| {
+            let mut fungible = transition(1, [Opout::new(genesis_id, ty, 0)]);
+            fungible.assignments = Assignments::from_inner(
+                Confined::try_from(BTreeMap::from([(
+                    ty,
+                    TypedAssigns::Fungible(
+                        SmallVec::try_from_iter(seals.into_iter().zip(values).map(
+                            |(seal, value)| {
+                                Assign::revealed(
+                                    XChain::Bitcoin(seal.into()),
+                                    RevealedValue::new_random_blinding(
+                                        value,
+                                        AssetTag::strict_dumb(),
+                                    ),
+                                )
+                            },
+                        ))
+                        .unwrap(),
+                    ),
+                )]))
+                .unwrap(),
+            );
+            let witness = bundled_witness(1, [fungible]);
+            let bundle_id = bundle_id(&witness);
+            let mut history = transfer([witness]);
+            // The last seal is a change, not sent to the receiver
+            history.terminals = SmallOrdMap::try_from_iter([(bundle_id, Terminal {
+                seals: SmallOrdSet::try_from_iter(
+                    seals[..2]
+                        .iter()
+                        .map(|seal| XChain::Bitcoin(TerminalSeal::from(*seal))),
+                )
+                .unwrap(),
+            })])
+            .unwrap();
+            history
+        };
+
+        assert_eq!(fungible([10, 20, u64::MAX]).total_by_type(ty).unwrap(), Amount::from(30u64));
+        assert_eq!(
+            fungible([u64::MAX / 2 + 1, u64::MAX / 2 + 1, 0])
+                .total_by_type(ty)
+                .unwrap_err(),
+            ConsistencyError::ValueOverflow(ty)
+        );
+    }
+
+    #[test]
+    fn validate_with_witnesses() {
+        #[derive(Default)]
+        struct CountingResolver(RefCell<Vec<XWitnessId>>);
+        impl ResolveWitness for CountingResolver {
+            fn resolve_pub_witness(
+                &self,
+                witness_id: XWitnessId,
+            ) -> Result<XWitnessTx, WitnessResolverError> {
+                self.0.borrow_mut().push(witness_id);
+                Err(WitnessResolverError::Unknown(witness_id))
+            }
+        }
+
+        let mut transfer = armored_transfer();
+        let testnet = transfer.genesis.testnet;
+        let opout = Opout::new(transfer.genesis.id(), AssignmentType::with(1), 0);
+        transfer.bundles =
+            LargeOrdSet::try_from_iter([bundled_witness(1, [transition(1, [opout])])]).unwrap();
+        let witness_id = transfer.bundles.first().unwrap().witness_id();
+
+        let resolver = CountingResolver::default();
+        let (status, transfer) = transfer
+            .validate_with_witnesses(&resolver, &none!(), testnet)
+            .unwrap_err();
+        assert_eq!(resolver.0.take(), vec![witness_id]);
+        assert_eq!(status.absent_pub_witnesses, vec![witness_id]);
+        assert!(status
+            .failures
+            .contains(&Failure::SealNoWitnessTx(witness_id)));
+
+        let cache = bmap! { witness_id => XWitnessTx::strict_dumb() };
+        let (status, _) = transfer
+            .validate_with_witnesses(&resolver, &cache, testnet)
+            .unwrap_err();
+        assert!(resolver.0.take().is_empty());
+        assert!(status.absent_pub_witnesses.is_empty());
+        assert!(!status
+            .failures
+            .contains(&Failure::SealNoWitnessTx(witness_id)));
+    }
 
     #[test]
     fn contract_str_round_trip() {
@@ -490,8 +2072,7 @@ Check-SHA256: aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa
 
     #[test]
     fn transfer_str_round_trip() {
-        let transfer = Transfer::from_str(include_str!("../../asset/armored_transfer.default"))
-            .expect("transfer from str should work");
+        let transfer = armored_transfer();
         assert_eq!(
             transfer.to_string(),
             include_str!("../../asset/armored_transfer.default"),
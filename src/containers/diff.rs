@@ -0,0 +1,187 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use rgb::{BundleId, OpId, Operation, TransitionBundle};
+use strict_encoding::{StrictEncode, StrictWriter};
+
+use super::{Consignment, TerminalDisclose};
+
+/// Differences between two consignments, as reported by
+/// [`Consignment::diff`].
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct ConsignmentDiff {
+    /// Bundles present only in the other consignment.
+    pub added_bundles: BTreeSet<BundleId>,
+    /// Bundles present only in this consignment.
+    pub removed_bundles: BTreeSet<BundleId>,
+    /// Terminal seals present only in the other consignment.
+    pub added_terminals: BTreeSet<TerminalDisclose>,
+    /// Terminal seals present only in this consignment.
+    pub removed_terminals: BTreeSet<TerminalDisclose>,
+    /// Operations present in both consignments, which differ in the revealed
+    /// seals or state.
+    pub reveal_mismatches: BTreeSet<OpId>,
+}
+
+impl ConsignmentDiff {
+    /// Detects whether the consignments have no differences.
+    pub fn is_empty(&self) -> bool { self == &Self::default() }
+}
+
+impl<const TRANSFER: bool> Consignment<TRANSFER> {
+    /// Compares the consignment with the `other` one, reporting bundles and
+    /// terminals present only in one of them and operations which are revealed
+    /// differently.
+    pub fn diff(&self, other: &Self) -> ConsignmentDiff {
+        fn bundles<const TRANSFER: bool>(
+            consignment: &Consignment<TRANSFER>,
+        ) -> BTreeMap<BundleId, &TransitionBundle> {
+            consignment
+                .bundles
+                .iter()
+                .flat_map(|bw| bw.bundles())
+                .map(|bundle| (bundle.bundle_id(), bundle))
+                .collect()
+        }
+
+        let ours = bundles(self);
+        let theirs = bundles(other);
+        let our_terminals = self.terminals_disclose().collect::<BTreeSet<_>>();
+        let their_terminals = other.terminals_disclose().collect::<BTreeSet<_>>();
+
+        // Operations equality ignores revealed data, so we compare their encoding
+        fn same_reveal(a: &impl StrictEncode, b: &impl StrictEncode) -> bool {
+            fn encode(val: &impl StrictEncode) -> Vec<u8> {
+                val.strict_encode(StrictWriter::in_memory::<{ usize::MAX }>())
+                    .expect("in-memory encoding")
+                    .unbox()
+                    .unconfine()
+            }
+            encode(a) == encode(b)
+        }
+
+        let mut reveal_mismatches = BTreeSet::new();
+        if !same_reveal(&self.genesis, &other.genesis) {
+            reveal_mismatches.insert(self.genesis.id());
+        }
+        for (bundle_id, bundle) in &ours {
+            let Some(other) = theirs.get(bundle_id) else {
+                continue;
+            };
+            for (opid, transition) in &bundle.known_transitions {
+                if matches!(other.known_transitions.get(opid), Some(t) if !same_reveal(t, transition))
+                {
+                    reveal_mismatches.insert(*opid);
+                }
+            }
+        }
+        let their_extensions = other
+            .extensions
+            .iter()
+            .map(|extension| (extension.id(), extension))
+            .collect::<BTreeMap<_, _>>();
+        for extension in &self.extensions {
+            let opid = extension.id();
+            if matches!(their_extensions.get(&opid), Some(e) if !same_reveal(*e, extension)) {
+                reveal_mismatches.insert(opid);
+            }
+        }
+
+        ConsignmentDiff {
+            added_bundles: theirs
+                .keys()
+                .filter(|id| !ours.contains_key(*id))
+                .copied()
+                .collect(),
+            removed_bundles: ours
+                .keys()
+                .filter(|id| !theirs.contains_key(*id))
+                .copied()
+                .collect(),
+            added_terminals: their_terminals
+                .difference(&our_terminals)
+                .cloned()
+                .collect(),
+            removed_terminals: our_terminals
+                .difference(&their_terminals)
+                .cloned()
+                .collect(),
+            reveal_mismatches,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use amplify::confinement::SmallOrdMap;
+    use amplify::ByteArray;
+    use bp::seals::txout::CloseMethod;
+    use rgb::{AssignmentType, GraphSeal, Opout, Transition, XChain};
+
+    use super::*;
+    use crate::containers::consignment::conceal_spent;
+    use crate::containers::consignment::test::{
+        bundle_id, bundled_witness, transfer, transition, with_seals,
+    };
+    use crate::containers::{Terminal, TerminalSeal};
+
+    #[test]
+    fn diff() {
+        let genesis_id = OpId::from_byte_array([0xAA; 32]);
+        let ty = AssignmentType::with(1);
+        let seal = GraphSeal::with_blinded_vout(CloseMethod::OpretFirst, 0u32, 1);
+        let revealed = with_seals(transition(1, [Opout::new(genesis_id, ty, 0)]), [seal]);
+        let opid = revealed.id();
+        let mut concealed = revealed.clone();
+        conceal_spent(&mut concealed.assignments, Some(&vec![Opout::new(opid, ty, 0)]));
+        let ours_only = transition(2, [Opout::new(genesis_id, ty, 1)]);
+        let theirs_only = transition(3, [Opout::new(genesis_id, ty, 2)]);
+
+        let mut ours = transfer([
+            bundled_witness(1, [revealed.clone()]),
+            bundled_witness(2, [ours_only.clone()]),
+        ]);
+        let mut theirs =
+            transfer([bundled_witness(1, [concealed]), bundled_witness(3, [theirs_only.clone()])]);
+        assert!(ours.diff(&ours).is_empty());
+
+        let shared_bundle = bundled_witness(1, [revealed]);
+        let shared_id = bundle_id(&shared_bundle);
+        let terminal = XChain::Bitcoin(TerminalSeal::conceal_from(seal));
+        ours.terminals =
+            SmallOrdMap::try_from_iter([(shared_id, Terminal::new(terminal))]).unwrap();
+        theirs.terminals = none!();
+
+        let bundle_id = |transition: Transition| bundle_id(&bundled_witness(0, [transition]));
+        let diff = ours.diff(&theirs);
+        assert_eq!(diff.added_bundles, bset![bundle_id(theirs_only)]);
+        assert_eq!(diff.removed_bundles, bset![bundle_id(ours_only)]);
+        assert!(diff.added_terminals.is_empty());
+        assert_eq!(diff.removed_terminals, bset![TerminalDisclose {
+            bundle_id: shared_id,
+            seal: terminal
+        }]);
+        assert_eq!(diff.reveal_mismatches, bset![opid]);
+    }
+}
@@ -19,5 +19,100 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-// TODO: Implement disclosures
-pub struct Disclosure;
+use std::collections::BTreeMap;
+
+use amplify::confinement::{self, LargeOrdMap, LargeOrdSet};
+use rgb::{BundleId, GraphSeal, TransitionBundle, XChain};
+
+use crate::{MergeReveal, MergeRevealError, LIB_NAME_RGB_STD};
+
+/// Disclosure reveals state which was concealed in the transition bundles
+/// already known to the other party, for instance revealing the amounts and
+/// seals of the change outputs to a co-signer of the wallet.
+///
+/// Unlike consignments, disclosures do not contain anchors or witnesses: they
+/// can be accepted only if the disclosed bundles are already present in the
+/// stash (see [`crate::persistence::Stock::accept_disclosure`]).
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+#[derive(StrictType, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STD)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct Disclosure {
+    /// Transition bundles with the revealed state.
+    pub bundles: LargeOrdMap<BundleId, TransitionBundle>,
+    /// Seals which are concealed in the state of the disclosed bundles or
+    /// other operations known to the receiver.
+    pub seals: LargeOrdSet<XChain<GraphSeal>>,
+}
+
+impl Disclosure {
+    pub fn is_empty(&self) -> bool { self.bundles.is_empty() && self.seals.is_empty() }
+}
+
+/// Builder constructing [`Disclosure`]s.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct DisclosureBuilder {
+    bundles: BTreeMap<BundleId, TransitionBundle>,
+    seals: Vec<XChain<GraphSeal>>,
+}
+
+impl DisclosureBuilder {
+    pub fn new() -> Self { Self::default() }
+
+    /// Adds bundle to the disclosure. If the bundle was already added, the
+    /// revealed data of both versions are merged.
+    pub fn add_bundle(mut self, bundle: TransitionBundle) -> Result<Self, MergeRevealError> {
+        let bundle_id = bundle.bundle_id();
+        let bundle = match self.bundles.remove(&bundle_id) {
+            Some(prev) => prev.merge_reveal(bundle)?,
+            None => bundle,
+        };
+        self.bundles.insert(bundle_id, bundle);
+        Ok(self)
+    }
+
+    /// Adds revealed seal to the disclosure.
+    pub fn add_seal(mut self, seal: XChain<GraphSeal>) -> Self {
+        self.seals.push(seal);
+        self
+    }
+
+    pub fn complete(self) -> Result<Disclosure, confinement::Error> {
+        Ok(Disclosure {
+            bundles: LargeOrdMap::try_from(self.bundles)?,
+            seals: LargeOrdSet::try_from_iter(self.seals)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bp::seals::txout::CloseMethod;
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+
+    #[test]
+    fn builder() {
+        let bundle = TransitionBundle::strict_dumb();
+        let seal = XChain::Bitcoin(GraphSeal::with_blinded_vout(CloseMethod::OpretFirst, 1u32, 42));
+        let disclosure = DisclosureBuilder::new()
+            .add_bundle(bundle.clone())
+            .unwrap()
+            .add_bundle(bundle.clone())
+            .unwrap()
+            .add_seal(seal)
+            .add_seal(seal)
+            .complete()
+            .unwrap();
+        assert!(!disclosure.is_empty());
+        assert_eq!(disclosure.bundles.len(), 1);
+        assert_eq!(disclosure.bundles.get(&bundle.bundle_id()), Some(&bundle));
+        assert_eq!(disclosure.seals.len(), 1);
+        assert!(Disclosure::default().is_empty());
+    }
+}
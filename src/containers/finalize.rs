@@ -0,0 +1,281 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::mem;
+
+use amplify::confinement::{LargeOrdSet, SmallOrdMap, SmallOrdSet};
+use commit_verify::Conceal;
+use rgb::{Assignments, ExposedSeal, OpId, Operation, XChain};
+
+use super::{
+    Consignment, ConsignmentId, ConsistencyError, Terminal, TerminalDisclose, TerminalSeal,
+};
+use crate::{SecretSeal, TypedAssignsExt};
+
+/// Changes which [`Consignment::finalize`] would apply to a consignment, as
+/// reported by [`Consignment::preview_finalize`].
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct FinalizePreview {
+    /// Terminal seals which will be removed from the consignment.
+    pub removed_terminals: Vec<TerminalDisclose>,
+    /// Number of assignments which seals will be concealed, per operation.
+    pub concealed: BTreeMap<OpId, usize>,
+    /// Id of the consignment after the finalization.
+    pub consignment_id: ConsignmentId,
+}
+
+impl FinalizePreview {
+    /// Total number of assignments which seals will be concealed.
+    pub fn concealed_count(&self) -> usize { self.concealed.values().sum() }
+}
+
+impl<const TRANSFER: bool> Consignment<TRANSFER> {
+    /// Finalizes consignment before sending it to the counterparty, removing
+    /// all terminal seals which are not present in `expose` set and concealing
+    /// revealed seals of all consignment operations, except the exposed ones.
+    ///
+    /// # Returns
+    ///
+    /// Number of assignments which seals were concealed.
+    pub fn finalize(&mut self, expose: &BTreeSet<XChain<TerminalSeal>>) -> usize {
+        let (_, concealed) = self.finalize_inner(expose);
+        concealed.values().sum()
+    }
+
+    /// Finalizes consignment like [`Consignment::finalize`], but fails if the
+    /// `expose` set contains seals which are not among the consignment
+    /// terminals.
+    ///
+    /// # Errors
+    ///
+    /// [`ConsistencyError::UnknownExposeTerminal`] with the first unknown
+    /// seal; the consignment is left unmodified in this case.
+    pub fn finalize_checked(
+        &mut self,
+        expose: &BTreeSet<XChain<TerminalSeal>>,
+    ) -> Result<usize, ConsistencyError> {
+        let terminals = self.terminal_seals().collect::<BTreeSet<_>>();
+        if let Some(seal) = expose.difference(&terminals).next() {
+            return Err(ConsistencyError::UnknownExposeTerminal(*seal));
+        }
+        Ok(self.finalize(expose))
+    }
+
+    /// Reports changes which [`Consignment::finalize`] would apply to the
+    /// consignment, without modifying it.
+    pub fn preview_finalize(&self, expose: &BTreeSet<XChain<TerminalSeal>>) -> FinalizePreview {
+        let mut finalized = self.clone();
+        let (removed_terminals, concealed) = finalized.finalize_inner(expose);
+        FinalizePreview {
+            removed_terminals,
+            concealed,
+            consignment_id: finalized.consignment_id(),
+        }
+    }
+
+    fn finalize_inner(
+        &mut self,
+        expose: &BTreeSet<XChain<TerminalSeal>>,
+    ) -> (Vec<TerminalDisclose>, BTreeMap<OpId, usize>) {
+        let removed_terminals = self
+            .terminals_disclose()
+            .filter(|terminal| !expose.contains(&terminal.seal))
+            .collect::<Vec<_>>();
+        let terminals = self
+            .terminals
+            .iter()
+            .filter_map(|(bundle_id, terminal)| {
+                let seals = terminal.seals.iter().filter(|seal| expose.contains(seal));
+                let seals = SmallOrdSet::from_iter_unsafe(seals.copied());
+                (!seals.is_empty()).then_some((*bundle_id, Terminal { seals }))
+            })
+            .collect::<BTreeMap<_, _>>();
+        self.terminals = SmallOrdMap::from_collection_unsafe(terminals);
+
+        fn conceal<Seal: ExposedSeal>(
+            assignments: &mut Assignments<Seal>,
+            except: &BTreeSet<XChain<SecretSeal>>,
+        ) -> usize {
+            assignments
+                .keyed_values_mut()
+                .map(|(_, assign)| assign.conceal_seals_except(except))
+                .sum()
+        }
+
+        let except = expose.iter().map(Conceal::conceal).collect::<BTreeSet<_>>();
+        let mut concealed = BTreeMap::new();
+
+        concealed.insert(self.genesis.id(), conceal(&mut self.genesis.assignments, &except));
+
+        let bundles = mem::take(&mut self.bundles).into_iter().map(|mut bw| {
+            for bundle in bw.anchored_bundles.bundles_mut() {
+                for (opid, transition) in bundle.known_transitions.keyed_values_mut() {
+                    concealed.insert(*opid, conceal(&mut transition.assignments, &except));
+                }
+            }
+            bw
+        });
+        self.bundles = LargeOrdSet::from_iter_unsafe(bundles);
+
+        let extensions = mem::take(&mut self.extensions)
+            .into_iter()
+            .map(|mut extension| {
+                concealed.insert(extension.id(), conceal(&mut extension.assignments, &except));
+                extension
+            });
+        self.extensions = LargeOrdSet::from_iter_unsafe(extensions);
+
+        concealed.retain(|_, count| *count > 0);
+        (removed_terminals, concealed)
+    }
+
+    /// Checks that the consignment is finalized, i.e. its operations do not
+    /// reveal seals other than the consignment terminals.
+    ///
+    /// # Errors
+    ///
+    /// With the first operation revealing non-terminal seals.
+    pub fn check_finalized(&self) -> Result<(), ConsistencyError> {
+        fn reveals_other<Seal: ExposedSeal>(
+            assignments: &Assignments<Seal>,
+            terminals: &BTreeSet<XChain<SecretSeal>>,
+        ) -> bool {
+            assignments.values().any(|assign| {
+                assign
+                    .filter_revealed_seals()
+                    .iter()
+                    .any(|seal| !terminals.contains(&seal.conceal()))
+            })
+        }
+
+        let terminals = self
+            .terminals_disclose()
+            .map(|terminal| terminal.seal.conceal())
+            .collect::<BTreeSet<_>>();
+        if reveals_other(&self.genesis.assignments, &terminals) {
+            return Err(ConsistencyError::NotFinalized(self.genesis.id()));
+        }
+        for bundle in self
+            .bundles
+            .iter()
+            .flat_map(|bw| bw.anchored_bundles.bundles())
+        {
+            for (opid, transition) in &bundle.known_transitions {
+                if reveals_other(&transition.assignments, &terminals) {
+                    return Err(ConsistencyError::NotFinalized(*opid));
+                }
+            }
+        }
+        for extension in &self.extensions {
+            if reveals_other(&extension.assignments, &terminals) {
+                return Err(ConsistencyError::NotFinalized(extension.id()));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use amplify::ByteArray;
+    use rgb::{AssignmentType, Opout};
+
+    use super::*;
+    use crate::containers::consignment::test::{
+        bundle_id, bundled_witness, transfer, transition, with_seals,
+    };
+    use crate::containers::VoutSeal;
+
+    #[test]
+    fn finalize_checked() {
+        let kept = VoutSeal::with_opret(0u32, 1);
+        let hidden = VoutSeal::with_opret(1u32, 2);
+        let genesis_id = OpId::from_byte_array([0xAA; 32]);
+        let assigning =
+            with_seals(transition(1, [Opout::new(genesis_id, AssignmentType::with(1), 0)]), [
+                kept.into(),
+                hidden.into(),
+            ]);
+        let witness = bundled_witness(1, [assigning]);
+        let bundle_id = bundle_id(&witness);
+
+        let mut history = transfer([witness]);
+        let kept = XChain::Bitcoin(TerminalSeal::from(kept));
+        history.terminals = SmallOrdMap::try_from_iter([(bundle_id, Terminal {
+            seals: SmallOrdSet::try_from_iter([kept]).unwrap(),
+        })])
+        .unwrap();
+
+        let bogus = XChain::Bitcoin(TerminalSeal::from(VoutSeal::with_opret(2u32, 3)));
+        let original = history.clone();
+        assert_eq!(
+            history.finalize_checked(&bset![kept, bogus]).unwrap_err(),
+            ConsistencyError::UnknownExposeTerminal(bogus)
+        );
+        assert_eq!(history, original);
+
+        assert_eq!(history.finalize_checked(&bset![kept]).unwrap(), 1);
+    }
+
+    #[test]
+    fn preview_finalize() {
+        let kept = VoutSeal::with_opret(0u32, 1);
+        let hidden = VoutSeal::with_opret(1u32, 2);
+        let genesis_id = OpId::from_byte_array([0xAA; 32]);
+        let transition =
+            with_seals(transition(1, [Opout::new(genesis_id, AssignmentType::with(1), 0)]), [
+                kept.into(),
+                hidden.into(),
+            ]);
+        let opid = transition.id();
+        let witness = bundled_witness(1, [transition]);
+        let bundle_id = bundle_id(&witness);
+
+        let mut transfer = transfer([witness]);
+        let kept = XChain::Bitcoin(TerminalSeal::from(kept));
+        let hidden = XChain::Bitcoin(TerminalSeal::from(hidden));
+        transfer.terminals = SmallOrdMap::try_from_iter([(bundle_id, Terminal {
+            seals: SmallOrdSet::try_from_iter([kept, hidden]).unwrap(),
+        })])
+        .unwrap();
+
+        let expose = bset![kept];
+        let preview = transfer.preview_finalize(&expose);
+        assert_eq!(preview.removed_terminals, vec![TerminalDisclose {
+            bundle_id,
+            seal: hidden
+        }]);
+        assert_eq!(preview.concealed, bmap! { opid => 1 });
+
+        assert_eq!(transfer.finalize(&expose), preview.concealed_count());
+        assert_eq!(transfer.consignment_id(), preview.consignment_id);
+        assert_eq!(
+            transfer
+                .terminals_disclose()
+                .map(|t| t.seal)
+                .collect::<Vec<_>>(),
+            vec![kept]
+        );
+        assert_eq!(transfer.operations()[1].opid(), opid);
+    }
+}
@@ -0,0 +1,320 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+
+use amplify::confinement::{Confined, U32};
+use rgb::{BundleId, Extension, Genesis, Metadata, Operation};
+use strict_encoding::{DecodeError, StreamReader, StrictDecode, StrictReader, TypedRead};
+
+use super::{BundledWitness, Consignment, ConsistencyError, ContainerVer, Terminal};
+
+/// Limits applied when decoding a consignment from an untrusted source with
+/// [`Consignment::strict_decode_limited`].
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct DecodeLimits {
+    /// Maximum number of bundled witnesses.
+    pub max_bundles: usize,
+    /// Maximum number of state extensions.
+    pub max_extensions: usize,
+    /// Maximum number of bundles referenced by terminals.
+    pub max_terminals: usize,
+    /// Maximum number of state transitions in a single transition bundle.
+    pub max_transitions: usize,
+    /// Maximum total size of the metadata values of a single operation, in
+    /// bytes.
+    pub max_metadata: usize,
+    /// Maximum size of the serialized consignment, in bytes.
+    pub max_bytes: usize,
+}
+
+impl Default for DecodeLimits {
+    /// Limits matching the confinement of the consignment data structures.
+    fn default() -> Self {
+        DecodeLimits {
+            max_bundles: u32::MAX as usize,
+            max_extensions: u32::MAX as usize,
+            max_terminals: u16::MAX as usize,
+            max_transitions: u16::MAX as usize,
+            max_metadata: u8::MAX as usize * u16::MAX as usize,
+            max_bytes: U32,
+        }
+    }
+}
+
+impl DecodeLimits {
+    fn check_metadata(&self, metadata: &Metadata) -> Result<(), ConsistencyError> {
+        let size = metadata
+            .into_iter()
+            .map(|(_, value)| value.len())
+            .sum::<usize>();
+        if size > self.max_metadata {
+            return Err(ConsistencyError::DecodeLimitExceeded {
+                kind: "metadata bytes",
+                limit: self.max_metadata,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl<const TRANSFER: bool> Consignment<TRANSFER> {
+    /// Decodes consignment from an untrusted source, checking collection sizes
+    /// against `limits` before decoding their items and stopping as soon as
+    /// the stream exceeds [`DecodeLimits::max_bytes`]. Each decoded bundle and
+    /// operation is checked against the per-item limits before the next one
+    /// is read. The stream must end right after the consignment data.
+    ///
+    /// For trusted data the unlimited [`StrictDecode`] implementation can be
+    /// used instead.
+    pub fn strict_decode_limited(
+        reader: impl io::Read,
+        limits: DecodeLimits,
+    ) -> Result<Self, ConsistencyError> {
+        let mut limited = reader.take(limits.max_bytes as u64);
+        let consignment = match Self::decode_limited(&mut limited, limits) {
+            Err(ConsistencyError::Decode(_)) if limited.limit() == 0 => {
+                return Err(ConsistencyError::DecodeLimitExceeded {
+                    kind: "bytes",
+                    limit: limits.max_bytes,
+                });
+            }
+            res => res?,
+        };
+        // The limit may be exhausted, so we check the source stream itself
+        let mut reader = limited.into_inner();
+        if reader.read(&mut [0u8]).map_err(DecodeError::from)? > 0 {
+            return Err(DecodeError::DataIntegrityError(s!(
+                "consignment data are followed by trailing bytes"
+            ))
+            .into());
+        }
+        Ok(consignment)
+    }
+
+    fn decode_limited(
+        reader: impl io::Read,
+        limits: DecodeLimits,
+    ) -> Result<Self, ConsistencyError> {
+        let mut reader = StrictReader::with(StreamReader::new::<U32>(reader));
+        let reader = &mut reader;
+
+        let version = ContainerVer::strict_decode(reader)?;
+        let transfer = bool::strict_decode(reader)?;
+
+        let len = u16::strict_decode(reader)? as usize;
+        if len > limits.max_terminals {
+            return Err(ConsistencyError::DecodeLimitExceeded {
+                kind: "terminals",
+                limit: limits.max_terminals,
+            });
+        }
+        let mut terminals = BTreeMap::new();
+        for _ in 0..len {
+            let bundle_id = BundleId::strict_decode(reader)?;
+            let terminal = Terminal::strict_decode(reader)?;
+            if matches!(terminals.last_key_value(), Some((last, _)) if last > &bundle_id) {
+                return Err(DecodeError::BrokenMapOrder.into());
+            }
+            if terminals.insert(bundle_id, terminal).is_some() {
+                return Err(DecodeError::RepeatedMapValue.into());
+            }
+        }
+
+        let genesis = Genesis::strict_decode(reader)?;
+        limits.check_metadata(genesis.metadata())?;
+        let extensions = decode_set_limited(
+            reader,
+            "extensions",
+            limits.max_extensions,
+            |extension: &Extension| limits.check_metadata(extension.metadata()),
+        )?;
+        let bundles =
+            decode_set_limited(reader, "bundles", limits.max_bundles, |bw: &BundledWitness| {
+                for bundle in bw.bundles() {
+                    if bundle.known_transitions.len() > limits.max_transitions {
+                        return Err(ConsistencyError::DecodeLimitExceeded {
+                            kind: "transitions",
+                            limit: limits.max_transitions,
+                        });
+                    }
+                    for transition in bundle.known_transitions.values() {
+                        limits.check_metadata(transition.metadata())?;
+                    }
+                }
+                Ok(())
+            })?;
+
+        Ok(Consignment {
+            version,
+            transfer,
+            terminals: Confined::from_collection_unsafe(terminals),
+            genesis,
+            extensions: Confined::from_collection_unsafe(extensions),
+            bundles: Confined::from_collection_unsafe(bundles),
+            schema: StrictDecode::strict_decode(reader)?,
+            ifaces: StrictDecode::strict_decode(reader)?,
+            supplements: StrictDecode::strict_decode(reader)?,
+            types: StrictDecode::strict_decode(reader)?,
+            scripts: StrictDecode::strict_decode(reader)?,
+            attachments: StrictDecode::strict_decode(reader)?,
+            signatures: StrictDecode::strict_decode(reader)?,
+        })
+    }
+}
+
+/// Decodes strict-encoded large ordered set, checking its length against the
+/// `limit` before decoding any of the items.
+fn decode_set_limited<T: StrictDecode + Ord>(
+    reader: &mut impl TypedRead,
+    kind: &'static str,
+    limit: usize,
+    check: impl Fn(&T) -> Result<(), ConsistencyError>,
+) -> Result<BTreeSet<T>, ConsistencyError> {
+    let len = u32::strict_decode(reader)? as usize;
+    if len > limit {
+        return Err(ConsistencyError::DecodeLimitExceeded { kind, limit });
+    }
+    let mut set = BTreeSet::new();
+    for _ in 0..len {
+        let item = T::strict_decode(reader)?;
+        check(&item)?;
+        if matches!(set.last(), Some(last) if last > &item) {
+            return Err(DecodeError::BrokenSetOrder.into());
+        }
+        if !set.insert(item) {
+            return Err(DecodeError::RepeatedSetValue.into());
+        }
+    }
+    Ok(set)
+}
+
+#[cfg(test)]
+mod test {
+
+    use amplify::confinement::SmallBlob;
+    use amplify::Wrapper;
+    use rgb::{AssignmentType, MetaType, MetaValue, Opout};
+    use strict_encoding::{StrictDumb, StrictSerialize};
+
+    use super::*;
+    use crate::containers::consignment::test::{
+        armored_transfer, bundled_witness, transfer, transition,
+    };
+    use crate::containers::Transfer;
+
+    #[test]
+    fn strict_decode_limited() {
+        let armored = armored_transfer();
+        let data = armored.to_strict_serialized::<U32>().unwrap();
+        let decoded = Transfer::strict_decode_limited(data.as_slice(), default!()).unwrap();
+        assert_eq!(decoded, armored);
+
+        let mut trailing = data.to_vec();
+        trailing.push(0);
+        assert!(matches!(
+            Transfer::strict_decode_limited(trailing.as_slice(), default!()),
+            Err(ConsistencyError::Decode(DecodeError::DataIntegrityError(_)))
+        ));
+        let limits = DecodeLimits {
+            max_bytes: data.len(),
+            ..default!()
+        };
+        assert!(matches!(
+            Transfer::strict_decode_limited(trailing.as_slice(), limits),
+            Err(ConsistencyError::Decode(DecodeError::DataIntegrityError(_)))
+        ));
+
+        let limits = DecodeLimits {
+            max_bytes: data.len() - 1,
+            ..default!()
+        };
+        assert_eq!(
+            Transfer::strict_decode_limited(data.as_slice(), limits).unwrap_err(),
+            ConsistencyError::DecodeLimitExceeded {
+                kind: "bytes",
+                limit: data.len() - 1
+            }
+        );
+
+        // Version, transfer flag and empty terminals precede the genesis, which
+        // is followed by the length of the extension set
+        let dumb = Transfer::strict_dumb();
+        let offset = 1 + 1 + 2 + dumb.genesis.to_strict_serialized::<U32>().unwrap().len();
+        let mut crafted = dumb.to_strict_serialized::<U32>().unwrap().into_inner();
+        assert_eq!(crafted[offset..offset + 4], [0u8; 4]);
+        crafted[offset..offset + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        let limits = DecodeLimits {
+            max_extensions: 1000,
+            ..default!()
+        };
+        assert_eq!(
+            Transfer::strict_decode_limited(crafted.as_slice(), limits).unwrap_err(),
+            ConsistencyError::DecodeLimitExceeded {
+                kind: "extensions",
+                limit: 1000
+            }
+        );
+    }
+
+    #[test]
+    fn strict_decode_limited_items() {
+        let ty = AssignmentType::with(1);
+        let genesis_id = Transfer::strict_dumb().genesis.id();
+        let first = transition(1, [Opout::new(genesis_id, ty, 0)]);
+        let mut second = transition(2, [Opout::new(genesis_id, ty, 1)]);
+        second
+            .metadata
+            .add_value(
+                MetaType::with(1),
+                MetaValue::from_inner(SmallBlob::try_from(vec![0; 16]).unwrap()),
+            )
+            .unwrap();
+        let transfer = transfer([bundled_witness(1, [first, second])]);
+        let data = transfer.to_strict_serialized::<U32>().unwrap();
+        assert_eq!(Transfer::strict_decode_limited(data.as_slice(), default!()).unwrap(), transfer);
+
+        let limits = DecodeLimits {
+            max_transitions: 1,
+            ..default!()
+        };
+        assert_eq!(
+            Transfer::strict_decode_limited(data.as_slice(), limits).unwrap_err(),
+            ConsistencyError::DecodeLimitExceeded {
+                kind: "transitions",
+                limit: 1
+            }
+        );
+
+        let limits = DecodeLimits {
+            max_metadata: 15,
+            ..default!()
+        };
+        assert_eq!(
+            Transfer::strict_decode_limited(data.as_slice(), limits).unwrap_err(),
+            ConsistencyError::DecodeLimitExceeded {
+                kind: "metadata bytes",
+                limit: 15
+            }
+        );
+    }
+}
@@ -31,6 +31,14 @@
 mod seal;
 mod anchors;
 mod consignment;
+mod diff;
+mod finalize;
+mod limits;
+mod operations;
+mod policy;
+mod streaming;
+mod subgraph;
+mod wrappers;
 mod disclosure;
 mod util;
 mod partials;
@@ -42,17 +50,27 @@ mod suppl;
 pub use anchors::{
     AnchorSet, AnchoredBundles, BundledWitness, PubWitness, SealWitness, ToWitnessId, XPubWitness,
 };
-pub use consignment::{
-    Consignment, ConsignmentId, Contract, Transfer, ValidConsignment, ValidContract, ValidTransfer,
-};
-pub use disclosure::Disclosure;
+#[cfg(all(test, feature = "fuzzing"))]
+pub(crate) use consignment::test::armored_transfer;
+pub use consignment::{Consignment, ConsignmentId, ConsistencyError, Contract, Transfer};
+pub use diff::ConsignmentDiff;
+pub use disclosure::{Disclosure, DisclosureBuilder};
 pub use file::{FileContent, LoadError, UniversalFile};
+pub use finalize::FinalizePreview;
 pub use indexed::IndexedConsignment;
 pub use kit::{Kit, KitId, ValidKit};
+pub use limits::DecodeLimits;
+pub use operations::ContractOperation;
 pub use partials::{
     Batch, BundleDichotomy, CloseMethodSet, Fascia, TransitionInfo, TransitionInfoError,
 };
-pub use seal::{BuilderSeal, TerminalSeal, VoutSeal};
+pub use policy::{ConflictingSpend, PolicyReport, PolicyWarning, ValidationPolicy, WarningPolicy};
+#[cfg(feature = "serde")]
+pub use seal::terminal_seal_string;
+pub use seal::{
+    BuilderSeal, ExternalSeal, LabeledSeal, SealDisclosure, SealMethodMismatch, TerminalSeal,
+    TerminalSealParseError, VoutSeal,
+};
 pub use suppl::{
     AnnotationName, Annotations, ContentRef, SupplId, SupplItem, SupplMap, SupplSub, Supplement,
     TickerSuppl, VelocityHint, SUPPL_ANNOT_IFACE_CLASS, SUPPL_ANNOT_IFACE_FEATURES,
@@ -62,6 +80,9 @@ pub use util::{
     ContainerVer, ContentId, ContentSigs, DumbValidator, SigBlob, SigValidator, Terminal,
     TerminalDisclose, TrustLevel,
 };
+pub use wrappers::{
+    InboundConsignment, OutboundConsignment, ValidConsignment, ValidContract, ValidTransfer,
+};
 
 pub const ASCII_ARMOR_NAME: &str = "Name";
 pub const ASCII_ARMOR_IFACE: &str = "Interface";
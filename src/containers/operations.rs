@@ -0,0 +1,230 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::iter;
+
+use rgb::{Extension, ExtensionType, OpId, Operation, TransitionType, XWitnessId};
+
+use super::{BundledWitness, Consignment};
+
+/// Operation of a contract, as listed in the consignment operation timeline
+/// (see [`Consignment::operations`]).
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum ContractOperation {
+    /// Contract genesis.
+    Genesis { opid: OpId },
+
+    /// State transition together with the id of its witness transaction.
+    Transition {
+        opid: OpId,
+        ty: TransitionType,
+        witness_id: XWitnessId,
+    },
+
+    /// State extension.
+    Extension { opid: OpId, ty: ExtensionType },
+}
+
+impl ContractOperation {
+    /// Returns id of the operation.
+    pub fn opid(&self) -> OpId {
+        match *self {
+            ContractOperation::Genesis { opid } |
+            ContractOperation::Transition { opid, .. } |
+            ContractOperation::Extension { opid, .. } => opid,
+        }
+    }
+}
+
+impl<const TRANSFER: bool> Consignment<TRANSFER> {
+    /// Lists all operations known to the consignment: genesis first, followed
+    /// by state transitions ordered by their witness id and operation id, and
+    /// then by state extensions ordered by their operation id.
+    pub fn operations(&self) -> Vec<ContractOperation> { self.iter_operations().collect() }
+
+    /// Lists a window of at most `limit` operations starting at `offset`,
+    /// using the same order as [`Self::operations`].
+    ///
+    /// Bundled witnesses preceding the window are skipped using the number of
+    /// their transitions, so only the witnesses overlapping the window have
+    /// their transitions listed.
+    pub fn operations_page(&self, offset: usize, limit: usize) -> Vec<ContractOperation> {
+        let mut page = Vec::with_capacity(limit.min(self.operations_len()));
+        let mut skip = offset;
+        if skip > 0 {
+            skip -= 1;
+        } else if limit > 0 {
+            page.push(ContractOperation::Genesis {
+                opid: self.genesis.id(),
+            });
+        }
+        for bw in &self.bundles {
+            if page.len() == limit {
+                return page;
+            }
+            let count = bw
+                .bundles()
+                .map(|bundle| bundle.known_transitions.len())
+                .sum::<usize>();
+            if skip >= count {
+                skip -= count;
+                continue;
+            }
+            page.extend(witness_transitions(bw).skip(skip).take(limit - page.len()));
+            skip = 0;
+        }
+        let extensions = self.extensions.iter().skip(skip);
+        page.extend(extensions.take(limit - page.len()).map(extension_operation));
+        page
+    }
+
+    /// Counts all operations known to the consignment, including genesis.
+    pub fn operations_len(&self) -> usize {
+        let transitions = self
+            .bundles
+            .iter()
+            .flat_map(|bw| bw.bundles())
+            .map(|bundle| bundle.known_transitions.len())
+            .sum::<usize>();
+        1 + transitions + self.extensions.len()
+    }
+
+    // Both bundled witnesses and extensions are stored in sets ordered by the
+    // witness id and operation id, so only transitions inside a single witness
+    // need sorting.
+    fn iter_operations(&self) -> impl Iterator<Item = ContractOperation> + '_ {
+        let genesis = ContractOperation::Genesis {
+            opid: self.genesis.id(),
+        };
+        let transitions = self.bundles.iter().flat_map(witness_transitions);
+        let extensions = self.extensions.iter().map(extension_operation);
+        iter::once(genesis).chain(transitions).chain(extensions)
+    }
+}
+
+/// Lists state transitions of the bundled witness ordered by their operation
+/// id.
+fn witness_transitions(bw: &BundledWitness) -> impl Iterator<Item = ContractOperation> {
+    let witness_id = bw.witness_id();
+    let mut transitions = bw
+        .bundles()
+        .flat_map(|bundle| bundle.known_transitions.iter())
+        .map(|(opid, transition)| (*opid, transition.transition_type))
+        .collect::<Vec<_>>();
+    transitions.sort();
+    transitions
+        .into_iter()
+        .map(move |(opid, ty)| ContractOperation::Transition {
+            opid,
+            ty,
+            witness_id,
+        })
+}
+
+fn extension_operation(extension: &Extension) -> ContractOperation {
+    ContractOperation::Extension {
+        opid: extension.id(),
+        ty: extension.extension_type,
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::collections::BTreeSet;
+
+    use amplify::ByteArray;
+    use rgb::{AssignmentType, Opout};
+
+    use super::*;
+    use crate::containers::consignment::test::{
+        armored_transfer, bundled_witness, transfer, transition,
+    };
+
+    #[test]
+    fn operations() {
+        let armored = armored_transfer();
+        let operations = armored.operations();
+        assert_eq!(operations[0], ContractOperation::Genesis {
+            opid: armored.genesis.id()
+        });
+
+        let opids = operations
+            .iter()
+            .map(ContractOperation::opid)
+            .collect::<BTreeSet<_>>();
+        assert_eq!(opids.len(), operations.len());
+        for bw in &armored.bundles {
+            for bundle in bw.bundles() {
+                for opid in bundle.known_transitions.keys() {
+                    assert!(opids.contains(opid));
+                }
+            }
+        }
+        for extension in &armored.extensions {
+            assert!(opids.contains(&extension.id()));
+        }
+
+        let genesis_id = OpId::from_byte_array([0xAA; 32]);
+        let ty = AssignmentType::with(1);
+        let first = transition(1, [Opout::new(genesis_id, ty, 0)]);
+        let second = transition(2, [Opout::new(first.id(), ty, 0)]);
+        let transfer =
+            transfer([bundled_witness(2, [second.clone()]), bundled_witness(1, [first.clone()])]);
+        let opids = transfer
+            .operations()
+            .iter()
+            .map(ContractOperation::opid)
+            .collect::<Vec<_>>();
+        assert_eq!(opids, vec![armored.genesis.id(), first.id(), second.id()]);
+    }
+
+    #[test]
+    fn operations_page() {
+        let armored = armored_transfer();
+        let genesis_id = OpId::from_byte_array([0xAA; 32]);
+        let ty = AssignmentType::with(1);
+        let history = transfer([
+            bundled_witness(2, [transition(1, [Opout::new(genesis_id, ty, 0)])]),
+            bundled_witness(1, [
+                transition(2, [Opout::new(genesis_id, ty, 1)]),
+                transition(3, [Opout::new(genesis_id, ty, 2)]),
+            ]),
+        ]);
+
+        for consignment in [armored, history] {
+            let operations = consignment.operations();
+            assert_eq!(consignment.operations_len(), operations.len());
+            for limit in 1..=3 {
+                let pages = (0..operations.len())
+                    .step_by(limit)
+                    .flat_map(|offset| consignment.operations_page(offset, limit))
+                    .collect::<Vec<_>>();
+                assert_eq!(pages, operations);
+            }
+            assert!(consignment.operations_page(operations.len(), 1).is_empty());
+            assert!(consignment.operations_page(0, 0).is_empty());
+            for offset in 0..=operations.len() {
+                assert_eq!(consignment.operations_page(offset, usize::MAX), operations[offset..]);
+            }
+        }
+    }
+}
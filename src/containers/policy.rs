@@ -0,0 +1,534 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::mem;
+
+use rgb::validation::{Failure, ResolveWitness, Validator, Validity, Warning};
+use rgb::{validation, BundleId, OpId, Opout, XChain};
+
+use super::{
+    Consignment, IndexedConsignment, SealMethodMismatch, TerminalDisclose, TerminalSeal,
+    ValidConsignment,
+};
+
+/// Treatment of a validation warning by the [`ValidationPolicy`].
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub enum WarningPolicy {
+    /// Warning is reported as a validation failure.
+    Fail,
+    /// Warning is kept in the validation status.
+    #[default]
+    Warn,
+    /// Warning is removed from the validation status.
+    Ignore,
+}
+
+/// Application-specific acceptance rules applied to the consignment on top of
+/// the consensus validation by [`Consignment::validate_with_policy`].
+///
+/// The default policy keeps all warnings and doesn't limit the history.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct ValidationPolicy {
+    /// Treatment of terminal seals absent from the operation assignments.
+    pub terminal_seal_absent: WarningPolicy,
+    /// Treatment of terminal witness transactions which are not mined yet.
+    pub terminal_witness_not_mined: WarningPolicy,
+    /// Treatment of all other warnings, including the ones reported by this
+    /// library.
+    pub other_warnings: WarningPolicy,
+    /// Maximum number of bundled witnesses in the consignment history.
+    pub max_bundles: Option<usize>,
+    /// Maximum depth of the state transition history; see
+    /// [`Consignment::history_depth`].
+    pub max_depth: Option<usize>,
+}
+
+impl ValidationPolicy {
+    /// Policy treating all warnings as validation failures.
+    pub fn strict() -> Self {
+        ValidationPolicy {
+            terminal_seal_absent: WarningPolicy::Fail,
+            terminal_witness_not_mined: WarningPolicy::Fail,
+            other_warnings: WarningPolicy::Fail,
+            ..default!()
+        }
+    }
+
+    /// Returns treatment of the `warning` under this policy.
+    pub fn warning_policy(&self, warning: &Warning) -> WarningPolicy {
+        match warning {
+            Warning::TerminalSealAbsent(..) => self.terminal_seal_absent,
+            Warning::TerminalWitnessNotMined(_) => self.terminal_witness_not_mined,
+            _ => self.other_warnings,
+        }
+    }
+
+    /// Returns treatment of the `warning` reported either by the consensus
+    /// validation or by this library under this policy.
+    pub fn policy_for(&self, warning: &PolicyWarning) -> WarningPolicy {
+        match warning {
+            PolicyWarning::Validation(warning) => self.warning_policy(warning),
+            _ => self.other_warnings,
+        }
+    }
+
+    /// Moves warnings from the validation `status`, together with the
+    /// `warnings` reported by this library, according to this policy.
+    ///
+    /// The validation status keeps escalated warnings only as custom failures;
+    /// their typed form is returned in [`PolicyReport::escalated`].
+    fn apply(
+        &self,
+        status: &mut validation::Status,
+        warnings: impl IntoIterator<Item = PolicyWarning>,
+    ) -> PolicyReport {
+        let mut report = PolicyReport::default();
+        let warnings = mem::take(&mut status.warnings)
+            .into_iter()
+            .map(PolicyWarning::Validation)
+            .chain(warnings);
+        for warning in warnings {
+            match self.policy_for(&warning) {
+                WarningPolicy::Fail => {
+                    status.add_failure(Failure::Custom(warning.to_string()));
+                    report.escalated.push(warning);
+                }
+                WarningPolicy::Warn => {
+                    status.add_warning(match &warning {
+                        PolicyWarning::Validation(warning) => warning.clone(),
+                        _ => Warning::Custom(warning.to_string()),
+                    });
+                    report.warnings.push(warning);
+                }
+                WarningPolicy::Ignore => {}
+            }
+        }
+        report
+    }
+}
+
+/// Warning reported either by the consensus validation or by the checks which
+/// this library performs on top of it, to which the [`ValidationPolicy`] is
+/// applied.
+#[derive(Clone, Eq, PartialEq, Debug, Display, From)]
+#[display(doc_comments)]
+pub enum PolicyWarning {
+    /// Warning of the consensus validation.
+    #[from]
+    #[display(inner)]
+    Validation(Warning),
+
+    /// terminal seal {seal} references bundle {bundle_id} which is absent from
+    /// the consignment
+    DanglingTerminal {
+        bundle_id: BundleId,
+        seal: XChain<TerminalSeal>,
+    },
+
+    /// terminal seal for bundle {0} is invalid: {1}
+    SealMethodMismatch(BundleId, SealMethodMismatch),
+
+    /// invalid consignment type
+    InvalidType,
+}
+
+/// State transitions spending the same owned state output, which is a
+/// client-side double spend.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Display)]
+#[display("state transitions {first} and {second} spend the same output {opout}")]
+pub struct ConflictingSpend {
+    /// Output spent by both state transitions.
+    pub opout: Opout,
+    /// Id of the first state transition.
+    pub first: OpId,
+    /// Id of the second state transition.
+    pub second: OpId,
+}
+
+/// Typed results of the checks performed by
+/// [`Consignment::validate_with_policy`] on top of the consensus validation.
+///
+/// The validation status contains these results only in their textual form,
+/// as custom warnings and failures.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct PolicyReport {
+    /// Client-side double spends, which are always reported as failures.
+    pub conflicting_spends: Vec<ConflictingSpend>,
+    /// Warnings kept by the policy.
+    pub warnings: Vec<PolicyWarning>,
+    /// Warnings escalated by the policy to failures.
+    pub escalated: Vec<PolicyWarning>,
+}
+
+impl<const TRANSFER: bool> Consignment<TRANSFER> {
+    /// Validates the consignment and applies the application-specific
+    /// `policy` to the validation results.
+    ///
+    /// The history size limits of the policy are checked before the
+    /// validation, so the consignments exceeding them are rejected without
+    /// resolving their witnesses.
+    ///
+    /// Besides the validation status, returns the [`PolicyReport`] with typed
+    /// results of the checks performed by this library; on success it is
+    /// available via [`ValidConsignment::policy_report`].
+    #[allow(clippy::result_large_err)]
+    pub fn validate_with_policy(
+        self,
+        resolver: &mut impl ResolveWitness,
+        testnet: bool,
+        policy: &ValidationPolicy,
+    ) -> Result<ValidConsignment<TRANSFER>, (validation::Status, PolicyReport, Consignment<TRANSFER>)>
+    {
+        if let Some(limit) = policy.max_bundles {
+            if self.bundles.len() > limit {
+                let failure = format!(
+                    "consignment contains {} bundles, exceeding the policy limit of {limit}",
+                    self.bundles.len()
+                );
+                let status = validation::Status::with_failure(Failure::Custom(failure));
+                return Err((status, PolicyReport::default(), self));
+            }
+        }
+        if let Some(limit) = policy.max_depth {
+            let depth = self.history_depth();
+            if depth > limit {
+                let failure = format!(
+                    "consignment history has depth {depth}, exceeding the policy limit of {limit}"
+                );
+                let status = validation::Status::with_failure(Failure::Custom(failure));
+                return Err((status, PolicyReport::default(), self));
+            }
+        }
+
+        let index = IndexedConsignment::new(&self);
+        let mut status = Validator::validate(&index, resolver, testnet);
+
+        let conflicting_spends = self.conflicting_spends();
+        for conflict in &conflicting_spends {
+            status.add_failure(Failure::Custom(conflict.to_string()));
+        }
+
+        let mut warnings = vec![];
+        for TerminalDisclose { bundle_id, seal } in self.dangling_terminals() {
+            warnings.push(PolicyWarning::DanglingTerminal { bundle_id, seal });
+        }
+        for (bundle_id, mismatch) in self.seal_method_mismatches() {
+            warnings.push(PolicyWarning::SealMethodMismatch(bundle_id, mismatch));
+        }
+        if self.transfer != TRANSFER {
+            warnings.push(PolicyWarning::InvalidType);
+        }
+
+        let mut report = policy.apply(&mut status, warnings);
+        report.conflicting_spends = conflicting_spends;
+        let validity = status.validity();
+
+        // TODO: check that interface ids match implementations
+        // TODO: check attach ids from data containers are present in operations
+        // TODO: validate sigs and remove untrusted
+
+        if validity != Validity::Valid {
+            Err((status, report, self))
+        } else {
+            Ok(ValidConsignment {
+                validation_status: status,
+                policy_report: report,
+                consignment: self,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use amplify::confinement::SmallOrdMap;
+    use amplify::ByteArray;
+    use bp::seals::txout::CloseMethod;
+    use bp::Txid;
+    use rgb::validation::WitnessResolverError;
+    use rgb::{AssignmentType, Operation, Opout, XWitnessId, XWitnessTx};
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+    use crate::containers::consignment::test::{
+        bundle_id, bundled_witness, transfer, transition, NoResolver,
+    };
+    use crate::containers::{AnchoredBundles, Terminal, Transfer, VoutSeal};
+
+    #[test]
+    fn conflicting_spends() {
+        let genesis_id = OpId::from_byte_array([0xAA; 32]);
+        let ty = AssignmentType::with(1);
+        let first = transition(1, [Opout::new(genesis_id, ty, 0)]);
+        let second = transition(2, [Opout::new(genesis_id, ty, 0)]);
+        let unrelated = transition(3, [Opout::new(genesis_id, ty, 1)]);
+
+        let valid = transfer([
+            bundled_witness(1, [first.clone()]),
+            bundled_witness(2, [unrelated.clone()]),
+        ]);
+        assert!(valid.conflicting_spends().is_empty());
+
+        let forked = transfer([
+            bundled_witness(1, [first.clone()]),
+            bundled_witness(2, [second.clone(), unrelated]),
+        ]);
+        let (a, b) = if first.id() < second.id() {
+            (first.id(), second.id())
+        } else {
+            (second.id(), first.id())
+        };
+        let conflict = ConflictingSpend {
+            opout: Opout::new(genesis_id, ty, 0),
+            first: a,
+            second: b,
+        };
+        assert_eq!(forked.conflicting_spends(), vec![conflict]);
+
+        struct NoResolver;
+        impl ResolveWitness for NoResolver {
+            fn resolve_pub_witness(
+                &self,
+                witness_id: XWitnessId,
+            ) -> Result<XWitnessTx, WitnessResolverError> {
+                Err(WitnessResolverError::Unknown(witness_id))
+            }
+        }
+        let failure = Failure::Custom(format!(
+            "state transitions {a} and {b} spend the same output {}",
+            conflict.opout
+        ));
+        let (status, _) = valid.validate(&mut NoResolver, true).unwrap_err();
+        assert!(!status.failures.contains(&failure));
+        let (status, forked) = forked.validate(&mut NoResolver, true).unwrap_err();
+        assert!(status.failures.contains(&failure));
+        let (status, report, _) = forked
+            .validate_with_policy(&mut NoResolver, true, &ValidationPolicy::strict())
+            .unwrap_err();
+        assert!(status.failures.contains(&failure));
+        assert_eq!(report.conflicting_spends, vec![conflict]);
+    }
+
+    #[test]
+    fn seal_method_mismatch() {
+        let genesis_id = OpId::from_byte_array([0xAA; 32]);
+        let mut witness = bundled_witness(1, [transition(1, [Opout::new(
+            genesis_id,
+            AssignmentType::with(1),
+            0,
+        )])]);
+        let AnchoredBundles::Opret(_, mut bundle) = witness.anchored_bundles else {
+            unreachable!()
+        };
+        bundle.close_method = CloseMethod::TapretFirst;
+        let bundle_id = bundle.bundle_id();
+        witness.anchored_bundles = AnchoredBundles::Tapret(strict_dumb!(), bundle);
+
+        let mut transfer = transfer([witness]);
+        let seal = XChain::Bitcoin(TerminalSeal::from(VoutSeal::with_opret(0u32, 0)));
+        transfer.terminals =
+            SmallOrdMap::try_from_iter([(bundle_id, Terminal::new(seal))]).unwrap();
+
+        let mismatch = SealMethodMismatch {
+            expected: CloseMethod::TapretFirst,
+            found: CloseMethod::OpretFirst,
+        };
+        assert_eq!(transfer.seal_method_mismatches(), vec![(bundle_id, mismatch)]);
+
+        let (status, transfer) = transfer.validate(&mut NoResolver, true).unwrap_err();
+        assert!(status.warnings.contains(&Warning::Custom(format!(
+            "terminal seal for bundle {bundle_id} is invalid: {mismatch}"
+        ))));
+
+        let (status, report, _) = transfer
+            .validate_with_policy(&mut NoResolver, true, &ValidationPolicy::default())
+            .unwrap_err();
+        let warning = PolicyWarning::SealMethodMismatch(bundle_id, mismatch);
+        assert!(report.warnings.contains(&warning));
+        assert!(status
+            .warnings
+            .contains(&Warning::Custom(warning.to_string())));
+    }
+
+    #[test]
+    fn invalid_type() {
+        let genesis_id = OpId::from_byte_array([0xAA; 32]);
+        let opout = Opout::new(genesis_id, AssignmentType::with(1), 0);
+        let mut transfer = transfer([bundled_witness(1, [transition(1, [opout])])]);
+        transfer.transfer = false;
+
+        let (status, report, transfer) = transfer
+            .validate_with_policy(&mut NoResolver, true, &ValidationPolicy::strict())
+            .unwrap_err();
+        assert!(report.escalated.contains(&PolicyWarning::InvalidType));
+        assert!(status
+            .failures
+            .contains(&Failure::Custom(s!("invalid consignment type"))));
+
+        let policy = ValidationPolicy {
+            other_warnings: WarningPolicy::Ignore,
+            ..default!()
+        };
+        let (status, report, _) = transfer
+            .validate_with_policy(&mut NoResolver, true, &policy)
+            .unwrap_err();
+        assert!(!report.warnings.contains(&PolicyWarning::InvalidType));
+        assert!(!status
+            .warnings
+            .contains(&Warning::Custom(s!("invalid consignment type"))));
+    }
+
+    #[test]
+    fn dangling_terminals() {
+        let genesis_id = OpId::from_byte_array([0xAA; 32]);
+        let opout = Opout::new(genesis_id, AssignmentType::with(1), 0);
+        let witness = bundled_witness(1, [transition(1, [opout])]);
+        let bundle_id = bundle_id(&witness);
+        let unknown_id = BundleId::from_byte_array([0xBB; 32]);
+        let seal = XChain::Bitcoin(TerminalSeal::from(VoutSeal::with_tapret(2u32, 7)));
+
+        let mut transfer = transfer([witness]);
+        transfer.terminals = SmallOrdMap::try_from_iter([
+            (bundle_id, Terminal::new(XChain::Bitcoin(VoutSeal::with_opret(1u32, 0).into()))),
+            (unknown_id, Terminal::new(seal)),
+        ])
+        .unwrap();
+        assert_eq!(transfer.dangling_terminals(), vec![TerminalDisclose {
+            bundle_id: unknown_id,
+            seal
+        }]);
+
+        let (status, transfer) = transfer.validate(&mut NoResolver, true).unwrap_err();
+        assert!(status.warnings.contains(&Warning::Custom(format!(
+            "terminal seal bc:tapret1st:~:2#0x00000007 references bundle {unknown_id} which is \
+             absent from the consignment"
+        ))));
+
+        let (status, report, _) = transfer
+            .validate_with_policy(&mut NoResolver, true, &ValidationPolicy::strict())
+            .unwrap_err();
+        let warning = PolicyWarning::DanglingTerminal {
+            bundle_id: unknown_id,
+            seal,
+        };
+        assert!(report.escalated.contains(&warning));
+        assert!(status
+            .failures
+            .contains(&Failure::Custom(warning.to_string())));
+        assert!(status.warnings.is_empty());
+    }
+
+    #[test]
+    fn validation_policy() {
+        struct PanickingResolver;
+        impl ResolveWitness for PanickingResolver {
+            fn resolve_pub_witness(
+                &self,
+                _: XWitnessId,
+            ) -> Result<XWitnessTx, WitnessResolverError> {
+                panic!("resolver must not be invoked")
+            }
+        }
+
+        let ty = AssignmentType::with(1);
+        let genesis_id = Transfer::strict_dumb().genesis.id();
+        let first = transition(1, [Opout::new(genesis_id, ty, 0)]);
+        let second = transition(2, [Opout::new(first.id(), ty, 0)]);
+        let transfer = transfer([bundled_witness(1, [first]), bundled_witness(2, [second])]);
+        assert_eq!(transfer.history_depth(), 2);
+
+        let policy = ValidationPolicy {
+            max_bundles: Some(1),
+            ..default!()
+        };
+        let (status, _, transfer) = transfer
+            .validate_with_policy(&mut PanickingResolver, true, &policy)
+            .unwrap_err();
+        assert_eq!(status.failures.len(), 1);
+
+        let policy = ValidationPolicy {
+            max_depth: Some(1),
+            ..default!()
+        };
+        let (status, _, _) = transfer
+            .validate_with_policy(&mut PanickingResolver, true, &policy)
+            .unwrap_err();
+        assert_eq!(status.failures.len(), 1);
+
+        let warnings = || {
+            let mut status = validation::Status::new();
+            status.add_warning(Warning::TerminalWitnessNotMined(Txid::from_byte_array([1; 32])));
+            status.add_warning(Warning::Custom(s!("custom")));
+            status
+        };
+        let not_mined = Warning::TerminalWitnessNotMined(Txid::from_byte_array([1; 32]));
+        let mut status = warnings();
+        let report = ValidationPolicy::default().apply(&mut status, []);
+        assert_eq!(status, warnings());
+        assert_eq!(report.warnings.len(), 2);
+        assert!(report.escalated.is_empty());
+
+        let mut status = warnings();
+        let report = ValidationPolicy {
+            terminal_witness_not_mined: WarningPolicy::Fail,
+            other_warnings: WarningPolicy::Ignore,
+            ..default!()
+        }
+        .apply(&mut status, [PolicyWarning::InvalidType]);
+        assert_eq!(status.failures.len(), 1);
+        assert!(status.warnings.is_empty());
+        assert_eq!(report.escalated, vec![PolicyWarning::Validation(not_mined)]);
+        assert!(report.warnings.is_empty());
+
+        let mut status = warnings();
+        let report = ValidationPolicy::strict().apply(&mut status, [PolicyWarning::InvalidType]);
+        assert_eq!(status.failures.len(), 3);
+        assert_eq!(status.validity(), Validity::Invalid);
+        assert_eq!(report.escalated.len(), 3);
+        assert!(report.escalated.contains(&PolicyWarning::InvalidType));
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn validation_status_json() {
+        let mut status = validation::Status::default();
+        status
+            .warnings
+            .push(Warning::Custom(s!("unknown terminal")));
+        status
+            .failures
+            .push(Failure::Custom(s!("conflicting spends")));
+
+        let json = serde_json::to_value(&status).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "absentPubWitnesses": [],
+                "unminedTerminals": [],
+                "failures": [{ "custom": "conflicting spends" }],
+                "warnings": [{ "custom": "unknown terminal" }],
+                "info": [],
+            })
+        );
+        assert_eq!(serde_json::from_value::<validation::Status>(json).unwrap(), status);
+    }
+}
@@ -21,9 +21,13 @@
 
 #![doc = include_str!("seals.md")]
 
-use bp::seals::txout::{BlindSeal, CloseMethod, SealTxid};
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use baid64::{Baid64ParseError, DisplayBaid64};
+use bp::seals::txout::{blind, BlindSeal, CloseMethod, SealTxid, TxPtr};
 use bp::secp256k1::rand::{thread_rng, RngCore};
-use bp::Vout;
+use bp::{Outpoint, Txid, Vout};
 use commit_verify::Conceal;
 use rgb::{GraphSeal, Layer1, SecretSeal, TxoSeal, XChain};
 
@@ -100,6 +104,118 @@ impl VoutSeal {
             blinding,
         }
     }
+
+    /// Checks that the seal commits to the `expected` close method.
+    pub fn validate_method(&self, expected: CloseMethod) -> Result<(), SealMethodMismatch> {
+        if self.method != expected {
+            return Err(SealMethodMismatch {
+                expected,
+                found: self.method,
+            });
+        }
+        Ok(())
+    }
+
+    /// Computes secret seal which this seal definition will have once the
+    /// witness transaction id becomes known.
+    pub fn disclosure_commitment(&self, txid: Txid) -> SecretSeal {
+        GraphSeal::with_blinding(self.method, txid, self.vout, self.blinding).conceal()
+    }
+
+    /// Constructs a record disclosing which transaction output corresponds to
+    /// the secret seal of this seal definition.
+    pub fn disclose(&self, txid: Txid) -> SealDisclosure {
+        SealDisclosure {
+            secret: self.disclosure_commitment(txid),
+            method: self.method,
+            outpoint: Outpoint::new(txid, self.vout),
+            blinding: self.blinding,
+        }
+    }
+}
+
+/// Seal definition with a local human-readable label, used for wallet
+/// bookkeeping. The label is never committed to or serialized into consensus
+/// data structures and is dropped on conversion into them.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct LabeledSeal {
+    pub seal: VoutSeal,
+    pub label: Option<String>,
+}
+
+impl LabeledSeal {
+    /// Creates new labeled seal definition for the provided output number and
+    /// seal closing method. Uses `thread_rng` to initialize blinding factor.
+    pub fn new(method: CloseMethod, vout: impl Into<Vout>, label: Option<String>) -> Self {
+        LabeledSeal {
+            seal: VoutSeal::new(method, vout),
+            label,
+        }
+    }
+}
+
+impl From<VoutSeal> for LabeledSeal {
+    fn from(seal: VoutSeal) -> Self { LabeledSeal { seal, label: None } }
+}
+
+impl From<LabeledSeal> for VoutSeal {
+    fn from(labeled: LabeledSeal) -> Self { labeled.seal }
+}
+
+impl From<LabeledSeal> for GraphSeal {
+    fn from(labeled: LabeledSeal) -> Self { labeled.seal.into() }
+}
+
+/// Revelation of a transaction output behind a secret seal, which can be
+/// verified without any additional data.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct SealDisclosure {
+    /// Secret seal which is disclosed.
+    pub secret: SecretSeal,
+
+    /// Close method of the seal definition.
+    pub method: CloseMethod,
+
+    /// Transaction output corresponding to the secret seal.
+    pub outpoint: Outpoint,
+
+    /// Blinding factor of the seal definition.
+    pub blinding: u64,
+}
+
+impl SealDisclosure {
+    /// Checks that the secret seal commits to the disclosed outpoint, close
+    /// method and blinding factor.
+    pub fn verify(&self) -> bool {
+        let seal = GraphSeal::with_blinding(
+            self.method,
+            self.outpoint.txid,
+            self.outpoint.vout,
+            self.blinding,
+        );
+        seal.conceal() == self.secret
+    }
+}
+
+/// seal is defined to be closed with {found} method, while the witness uses
+/// {expected} commitment.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub struct SealMethodMismatch {
+    /// Close method used by the witness commitment.
+    pub expected: CloseMethod,
+    /// Close method committed to by the seal definition.
+    pub found: CloseMethod,
 }
 
 impl From<VoutSeal> for GraphSeal {
@@ -108,6 +224,46 @@ impl From<VoutSeal> for GraphSeal {
     }
 }
 
+impl Display for VoutSeal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result { Display::fmt(&GraphSeal::from(*self), f) }
+}
+
+impl FromStr for VoutSeal {
+    type Err = TerminalSealParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let seal = GraphSeal::from_str(s)?;
+        if seal.txid != TxPtr::WitnessTx {
+            return Err(TerminalSealParseError::NotWitnessVout);
+        }
+        Ok(VoutSeal::with(seal.method, seal.vout, seal.blinding))
+    }
+}
+
+/// errors parsing terminal seal string representation.
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum TerminalSealParseError {
+    /// seal must point to the witness transaction output using `~` in place
+    /// of the transaction id.
+    NotWitnessVout,
+
+    #[from]
+    #[display(inner)]
+    Seal(blind::ParseError),
+
+    /// invalid secret seal - {0}
+    Secret(Baid64ParseError),
+
+    /// '{input}' is neither a valid secret seal ({secret}) nor a witness
+    /// output seal ({seal}).
+    Unrecognized {
+        input: String,
+        secret: Baid64ParseError,
+        seal: blind::ParseError,
+    },
+}
+
 /// Seal endpoint is a confidential seal which may be linked to the witness
 /// transaction, but does not contain information about its id.
 ///
@@ -145,6 +301,19 @@ impl TerminalSeal {
         TerminalSeal::WitnessVout(VoutSeal::new(method, vout))
     }
 
+    /// Constructs [`TerminalSeal`] from a revealed seal. Seals pointing to the
+    /// witness transaction output are kept revealed, while seals pointing to
+    /// an external transaction output are concealed, losing their outpoint,
+    /// close method and blinding.
+    pub fn conceal_from(seal: GraphSeal) -> Self {
+        match seal.txid {
+            TxPtr::WitnessTx => {
+                TerminalSeal::WitnessVout(VoutSeal::with(seal.method, seal.vout, seal.blinding))
+            }
+            TxPtr::Txid(_) => TerminalSeal::ConcealedUtxo(seal.conceal()),
+        }
+    }
+
     pub fn secret_seal(&self) -> Option<SecretSeal> {
         match self {
             TerminalSeal::ConcealedUtxo(seal) => Some(*seal),
@@ -153,6 +322,64 @@ impl TerminalSeal {
     }
 }
 
+impl Display for TerminalSeal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TerminalSeal::ConcealedUtxo(seal) => Display::fmt(seal, f),
+            TerminalSeal::WitnessVout(seal) => Display::fmt(seal, f),
+        }
+    }
+}
+
+impl FromStr for TerminalSeal {
+    type Err = TerminalSealParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let secret = match SecretSeal::from_str(s) {
+            Ok(seal) => return Ok(TerminalSeal::ConcealedUtxo(seal)),
+            Err(err)
+                if s.strip_prefix(SecretSeal::HRI)
+                    .is_some_and(|rest| rest.starts_with(':')) =>
+            {
+                return Err(TerminalSealParseError::Secret(err));
+            }
+            Err(err) => err,
+        };
+        match VoutSeal::from_str(s) {
+            Ok(seal) => Ok(TerminalSeal::WitnessVout(seal)),
+            Err(TerminalSealParseError::Seal(seal)) => Err(TerminalSealParseError::Unrecognized {
+                input: s.to_owned(),
+                secret,
+                seal,
+            }),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+/// Serde (de)serialization of [`TerminalSeal`] using its compact string
+/// representation instead of the default derived structure.
+///
+/// Use with `#[serde(with = "rgbstd::containers::terminal_seal_string")]`.
+#[cfg(feature = "serde")]
+pub mod terminal_seal_string {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::*;
+
+    pub fn serialize<S: Serializer>(seal: &TerminalSeal, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(seal)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<TerminalSeal, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        TerminalSeal::from_str(&s).map_err(D::Error::custom)
+    }
+}
+
 impl Conceal for TerminalSeal {
     type Concealed = SecretSeal;
 
@@ -184,3 +411,188 @@ impl<Seal: TxoSeal + Ord> BuilderSeal<Seal> {
         }
     }
 }
+
+/// Converts terminal seal into a builder seal. The conversion is lossless: the
+/// witness output seal keeps its close method, output number and blinding.
+impl From<XChain<TerminalSeal>> for BuilderSeal<GraphSeal> {
+    fn from(seal: XChain<TerminalSeal>) -> Self {
+        match seal.as_reduced_unsafe() {
+            TerminalSeal::ConcealedUtxo(secret) => {
+                BuilderSeal::Concealed(XChain::with(seal.layer1(), *secret))
+            }
+            TerminalSeal::WitnessVout(vout) => {
+                BuilderSeal::Revealed(XChain::with(seal.layer1(), GraphSeal::from(*vout)))
+            }
+        }
+    }
+}
+
+/// seal points to the external transaction output {0} and can't be used as a
+/// terminal seal without being concealed.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub struct ExternalSeal(pub Outpoint);
+
+/// Converts builder seal into a terminal seal.
+///
+/// Revealed seals pointing to an external transaction output are rejected,
+/// since a terminal seal can keep them only in the concealed form, losing the
+/// outpoint, close method and blinding. Use [`TerminalSeal::conceal_from`] to
+/// conceal such seals explicitly.
+impl TryFrom<BuilderSeal<GraphSeal>> for XChain<TerminalSeal> {
+    type Error = ExternalSeal;
+
+    fn try_from(seal: BuilderSeal<GraphSeal>) -> Result<Self, Self::Error> {
+        match seal {
+            BuilderSeal::Concealed(secret) => Ok(secret.map(TerminalSeal::ConcealedUtxo)),
+            BuilderSeal::Revealed(seal) => seal.try_map(|seal| match seal.txid {
+                TxPtr::WitnessTx => Ok(TerminalSeal::conceal_from(seal)),
+                TxPtr::Txid(txid) => Err(ExternalSeal(Outpoint::new(txid, seal.vout))),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::ByteArray;
+
+    use super::*;
+
+    #[test]
+    fn seal_disclosure() {
+        let txid = Txid::from_byte_array([0xAB; 32]);
+        let seal = VoutSeal::new_tapret(1u32);
+        let mut disclosure = seal.disclose(txid);
+        assert_eq!(disclosure.secret, seal.disclosure_commitment(txid));
+        assert!(disclosure.verify());
+
+        disclosure.outpoint.vout = Vout::from_u32(2);
+        assert!(!disclosure.verify());
+    }
+
+    #[test]
+    fn labeled_seal() {
+        let labeled = LabeledSeal::new(CloseMethod::TapretFirst, 1u32, Some(s!("change")));
+        let seal = labeled.seal;
+        assert_eq!(GraphSeal::from(labeled.clone()), GraphSeal::from(seal));
+        assert_eq!(VoutSeal::from(labeled), seal);
+        assert_eq!(LabeledSeal::from(seal).label, None);
+    }
+
+    #[test]
+    fn builder_seal_conversions() {
+        let vout = XChain::Bitcoin(TerminalSeal::from(VoutSeal::with_tapret(3u32, 0x1234)));
+        let BuilderSeal::Revealed(XChain::Bitcoin(revealed)) = BuilderSeal::from(vout) else {
+            panic!("witness output seal must stay revealed");
+        };
+        assert_eq!(revealed.method, CloseMethod::TapretFirst);
+        assert_eq!(revealed.txid, TxPtr::WitnessTx);
+        assert_eq!(revealed.vout, Vout::from_u32(3));
+        assert_eq!(revealed.blinding, 0x1234);
+        assert_eq!(XChain::try_from(BuilderSeal::from(vout)), Ok(vout));
+
+        let secret =
+            GraphSeal::new_random(CloseMethod::OpretFirst, Txid::from_byte_array([1; 32]), 0)
+                .conceal();
+        let concealed = XChain::Liquid(TerminalSeal::ConcealedUtxo(secret));
+        assert_eq!(
+            BuilderSeal::from(concealed),
+            BuilderSeal::<GraphSeal>::Concealed(XChain::Liquid(secret))
+        );
+        assert_eq!(XChain::try_from(BuilderSeal::from(concealed)), Ok(concealed));
+
+        let txid = Txid::from_byte_array([2; 32]);
+        let external = GraphSeal::new_random(CloseMethod::OpretFirst, txid, 1);
+        assert_eq!(
+            XChain::<TerminalSeal>::try_from(BuilderSeal::Revealed(XChain::Bitcoin(external))),
+            Err(ExternalSeal(Outpoint::new(txid, 1)))
+        );
+    }
+
+    #[test]
+    fn terminal_seal_str_round_trip() {
+        let vout = TerminalSeal::from(VoutSeal::with_tapret(3u32, 0x1234));
+        assert_eq!(vout.to_string(), "tapret1st:~:3#0x00001234");
+        let concealed = TerminalSeal::ConcealedUtxo(
+            VoutSeal::with_opret(1u32, 42).disclosure_commitment(Txid::from_byte_array([1; 32])),
+        );
+        for seal in [vout, concealed] {
+            assert_eq!(TerminalSeal::from_str(&seal.to_string()).unwrap(), seal);
+        }
+        assert!(matches!(
+            TerminalSeal::from_str(
+                &GraphSeal::new_random(
+                    CloseMethod::OpretFirst,
+                    Txid::from_byte_array([1; 32]),
+                    0u32
+                )
+                .to_string()
+            ),
+            Err(TerminalSealParseError::NotWitnessVout)
+        ));
+    }
+
+    #[test]
+    fn terminal_seal_unrecognized() {
+        let err = TerminalSeal::from_str("garbage").unwrap_err();
+        let TerminalSealParseError::Unrecognized {
+            input,
+            secret,
+            seal,
+        } = &err
+        else {
+            panic!("both parse attempts must be reported, got {err:?}");
+        };
+        assert_eq!(input, "garbage");
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "'garbage' is neither a valid secret seal ({secret}) nor a witness output seal \
+                 ({seal})."
+            )
+        );
+    }
+
+    #[test]
+    fn terminal_seal_invalid_secret() {
+        let secret = VoutSeal::with_opret(1u32, 42)
+            .disclosure_commitment(Txid::from_byte_array([1; 32]))
+            .to_string();
+        let corrupted = format!("{}x", &secret[..secret.len() - 1]);
+        assert!(matches!(
+            TerminalSeal::from_str(&corrupted),
+            Err(TerminalSealParseError::Secret(_))
+        ));
+    }
+
+    #[test]
+    fn terminal_seal_conceal_from() {
+        let vout = VoutSeal::with_tapret(3u32, 0x1234);
+        assert_eq!(TerminalSeal::conceal_from(GraphSeal::from(vout)), TerminalSeal::from(vout));
+
+        let external = GraphSeal::with_blinding(
+            CloseMethod::OpretFirst,
+            Txid::from_byte_array([1; 32]),
+            2u32,
+            0x42,
+        );
+        assert_eq!(
+            TerminalSeal::conceal_from(external),
+            TerminalSeal::ConcealedUtxo(external.conceal())
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn terminal_seal_serde_string() {
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        #[serde(crate = "serde_crate")]
+        struct Wrapper(#[serde(with = "terminal_seal_string")] TerminalSeal);
+
+        let seal = Wrapper(TerminalSeal::from(VoutSeal::with_opret(1u32, 0xff)));
+        let json = serde_json::to_string(&seal).unwrap();
+        assert_eq!(json, r#""opret1st:~:1#0x000000ff""#);
+        assert_eq!(serde_json::from_str::<Wrapper>(&json).unwrap(), seal);
+    }
+}
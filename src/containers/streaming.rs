@@ -0,0 +1,242 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeSet;
+use std::io;
+
+use aluvm::library::Lib;
+use amplify::confinement::{
+    Confined, LargeOrdSet, MediumBlob, SmallOrdMap, SmallOrdSet, TinyOrdMap, TinyOrdSet, U32,
+};
+use commit_verify::{CommitEngine, CommitmentId};
+use rgb::validation::CONSIGNMENT_MAX_LIBS;
+use rgb::{AttachId, BundleId, Extension, Genesis, Operation, Schema};
+use strict_encoding::{
+    DecodeError, StreamReader, StreamWriter, StrictDecode, StrictEncode, StrictReader,
+    StrictWriter, TypedRead,
+};
+use strict_types::TypeSystem;
+
+use super::consignment::ConsignmentCommitment;
+use super::{
+    BundledWitness, Consignment, ConsignmentId, ContainerVer, ContentId, ContentSigs, Supplement,
+    Terminal, TerminalDisclose,
+};
+use crate::interface::{Iface, IfaceImpl};
+
+impl<const TRANSFER: bool> Consignment<TRANSFER> {
+    /// Computes consignment id directly from a strict-encoded consignment byte
+    /// stream, without materializing the whole consignment in memory.
+    ///
+    /// Bundles, extensions and attachments are decoded one by one and only
+    /// their commitments are retained, so the memory footprint does not depend
+    /// on the size of the consignment history. The retained data are committed
+    /// with the same procedure as used by the [`CommitEncode`] implementation
+    /// for [`Consignment`].
+    pub fn consignment_id_streaming(reader: impl io::Read) -> Result<ConsignmentId, DecodeError> {
+        let mut reader = StrictReader::with(StreamReader::new::<U32>(reader));
+        let reader = &mut reader;
+
+        let version = ContainerVer::strict_decode(reader)?;
+        let transfer = bool::strict_decode(reader)?;
+        let terminals = SmallOrdMap::<BundleId, Terminal>::strict_decode(reader)?;
+        let genesis = Genesis::strict_decode(reader)?;
+        let extensions = decode_set_streaming::<Extension, _>(reader, Extension::disclose_hash)?;
+        let bundles =
+            decode_set_streaming::<BundledWitness, _>(reader, BundledWitness::disclose_hash)?;
+        Schema::strict_decode(reader)?;
+        let ifaces = TinyOrdMap::<Iface, IfaceImpl>::strict_decode(reader)?;
+        let supplements = TinyOrdSet::<Supplement>::strict_decode(reader)?;
+        let types = TypeSystem::strict_decode(reader)?;
+        let scripts = Confined::<BTreeSet<Lib>, 0, CONSIGNMENT_MAX_LIBS>::strict_decode(reader)?;
+        let mut attachments = BTreeSet::new();
+        for _ in 0..u16::strict_decode(reader)? {
+            let id = AttachId::strict_decode(reader)?;
+            MediumBlob::strict_decode(reader)?;
+            if matches!(attachments.last(), Some(last) if last > &id) {
+                return Err(DecodeError::BrokenMapOrder);
+            }
+            if !attachments.insert(id) {
+                return Err(DecodeError::RepeatedMapValue);
+            }
+        }
+        let signatures = TinyOrdMap::<ContentId, ContentSigs>::strict_decode(reader)?;
+
+        let mut e = CommitEngine::new(ConsignmentId::TAG);
+        ConsignmentCommitment {
+            version,
+            transfer,
+            contract_id: genesis.contract_id(),
+            genesis: genesis.disclose_hash(),
+            ifaces: TinyOrdSet::from_iter_unsafe(ifaces.values().map(IfaceImpl::impl_id)),
+            bundles: LargeOrdSet::from_iter_unsafe(bundles),
+            extensions: LargeOrdSet::from_iter_unsafe(extensions),
+            terminals: SmallOrdSet::from_iter_unsafe(terminals.iter().flat_map(|(id, term)| {
+                term.seals.iter().map(|seal| TerminalDisclose {
+                    bundle_id: *id,
+                    seal: *seal,
+                })
+            })),
+            attachments: SmallOrdSet::from_iter_unsafe(attachments),
+            supplements: TinyOrdSet::from_iter_unsafe(supplements.iter().map(Supplement::suppl_id)),
+            types: types.id(),
+            scripts: SmallOrdSet::from_iter_unsafe(scripts.iter().map(Lib::id)),
+            signatures: &signatures,
+        }
+        .commit_encode(&mut e);
+        e.set_finished();
+
+        Ok(e.finish().into())
+    }
+
+    /// Writes strict-encoded consignment into the `writer` section by section,
+    /// without serializing the whole consignment in memory. The produced data
+    /// are identical to the [`StrictSerialize`] output.
+    ///
+    /// The `progress` callback receives the total number of bytes written so
+    /// far after the header (version, type and terminals), the genesis, the
+    /// state extensions, each of the bundles, the schema and the rest of the
+    /// consignment data.
+    ///
+    /// # Returns
+    ///
+    /// Total number of bytes written.
+    pub fn strict_encode_streaming(
+        &self,
+        writer: impl io::Write,
+        mut progress: impl FnMut(usize),
+    ) -> Result<usize, io::Error> {
+        let mut counter = WriteCounter { writer, count: 0 };
+        macro_rules! section {
+            ($($field:expr),+) => {
+                {
+                    let mut writer = StrictWriter::with(StreamWriter::new::<U32>(&mut counter));
+                    $( writer = $field.strict_encode(writer)?; )+
+                    let _ = writer;
+                }
+                progress(counter.count);
+            };
+        }
+
+        section!(self.version, self.transfer, self.terminals);
+        section!(self.genesis);
+        section!(self.extensions);
+        section!(self.bundles.len_u32());
+        for bundle in &self.bundles {
+            section!(bundle);
+        }
+        section!(self.schema);
+        section!(
+            self.ifaces,
+            self.supplements,
+            self.types,
+            self.scripts,
+            self.attachments,
+            self.signatures
+        );
+        Ok(counter.count)
+    }
+}
+
+/// Writer counting bytes passed to the underlying writer.
+struct WriteCounter<W: io::Write> {
+    writer: W,
+    count: usize,
+}
+
+impl<W: io::Write> io::Write for WriteCounter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = self.writer.write(buf)?;
+        self.count += len;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> { self.writer.flush() }
+}
+
+/// Decodes strict-encoded large ordered set item by item, retaining only the
+/// values produced by `f` for each of them.
+fn decode_set_streaming<T: StrictDecode + Ord, H: Ord>(
+    reader: &mut impl TypedRead,
+    f: impl Fn(&T) -> H,
+) -> Result<BTreeSet<H>, DecodeError> {
+    let len = u32::strict_decode(reader)?;
+    let mut set = BTreeSet::new();
+    let mut last = None::<T>;
+    for _ in 0..len {
+        let item = T::strict_decode(reader)?;
+        match &last {
+            Some(prev) if prev > &item => return Err(DecodeError::BrokenSetOrder),
+            Some(prev) if prev == &item => return Err(DecodeError::RepeatedSetValue),
+            _ => {}
+        }
+        set.insert(f(&item));
+        last = Some(item);
+    }
+    Ok(set)
+}
+
+#[cfg(test)]
+mod test {
+
+    use amplify::ByteArray;
+    use rgb::{AssignmentType, OpId, Opout};
+    use strict_encoding::StrictSerialize;
+
+    use super::*;
+    use crate::containers::consignment::test::{
+        armored_transfer, bundled_witness, transfer, transition,
+    };
+    use crate::containers::Transfer;
+
+    #[test]
+    fn strict_encode_streaming() {
+        let genesis_id = OpId::from_byte_array([0xAA; 32]);
+        let ty = AssignmentType::with(1);
+        let history = transfer([
+            bundled_witness(1, [transition(1, [Opout::new(genesis_id, ty, 0)])]),
+            bundled_witness(2, [transition(2, [Opout::new(genesis_id, ty, 1)])]),
+        ]);
+        let data = history.to_strict_serialized::<U32>().unwrap();
+
+        let mut streamed = vec![];
+        let mut checkpoints = vec![];
+        let len = history
+            .strict_encode_streaming(&mut streamed, |count| checkpoints.push(count))
+            .unwrap();
+        assert_eq!(streamed, data.as_slice());
+        assert_eq!(len, data.len());
+        // header, genesis, extensions, bundle count, two bundles, schema, rest
+        assert_eq!(checkpoints.len(), 8);
+        assert!(checkpoints.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(checkpoints.last(), Some(&data.len()));
+    }
+
+    #[test]
+    fn consignment_id_streaming() {
+        let transfer = armored_transfer();
+        let data = transfer.to_strict_serialized::<U32>().unwrap();
+        assert_eq!(
+            Transfer::consignment_id_streaming(data.as_slice()).unwrap(),
+            transfer.consignment_id()
+        );
+    }
+}
@@ -0,0 +1,313 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use amplify::confinement::{Confined, LargeOrdSet, SmallOrdMap, SmallOrdSet};
+use commit_verify::Conceal;
+use rgb::{BundleId, GraphSeal, OpId, Operation, Transition, XChain};
+
+use super::{Consignment, ConsistencyError, Terminal, TerminalSeal};
+use crate::SecretSeal;
+
+impl<const TRANSFER: bool> Consignment<TRANSFER> {
+    /// Extracts part of the consignment history relevant to the provided
+    /// seals: state transitions assigning state to any of the seals and all
+    /// operations on the path from them to the genesis. The terminals of the
+    /// produced consignment point to the provided seals.
+    ///
+    /// # Errors
+    ///
+    /// If none of the seals is assigned by the consignment transitions, or if
+    /// the history of the matching transitions is incomplete.
+    pub fn subgraph_for<'seal>(
+        &self,
+        seals: impl IntoIterator<Item = &'seal XChain<GraphSeal>>,
+    ) -> Result<Self, ConsistencyError> {
+        let seals = seals
+            .into_iter()
+            .map(|seal| (seal.conceal(), seal.map(TerminalSeal::conceal_from)))
+            .collect::<BTreeMap<_, _>>();
+        self.subgraph_inner(&seals)
+    }
+
+    fn subgraph_inner(
+        &self,
+        seals: &BTreeMap<XChain<SecretSeal>, XChain<TerminalSeal>>,
+    ) -> Result<Self, ConsistencyError> {
+        let mut transitions = BTreeMap::<OpId, (BundleId, &Transition)>::new();
+        for bw in &self.bundles {
+            for bundle in bw.bundles() {
+                for (opid, transition) in &bundle.known_transitions {
+                    transitions.insert(*opid, (bundle.bundle_id(), transition));
+                }
+            }
+        }
+        let extensions = self
+            .extensions
+            .iter()
+            .map(|extension| (extension.id(), extension))
+            .collect::<BTreeMap<_, _>>();
+
+        let mut terminals = BTreeMap::<BundleId, BTreeSet<XChain<TerminalSeal>>>::new();
+        let mut queue = vec![];
+        for (opid, (bundle_id, transition)) in &transitions {
+            for assigns in transition.assignments.values() {
+                for secret in assigns.to_confidential_seals() {
+                    if let Some(seal) = seals.get(&secret) {
+                        terminals.entry(*bundle_id).or_default().insert(*seal);
+                        queue.push(*opid);
+                    }
+                }
+            }
+        }
+        if queue.is_empty() {
+            return Err(ConsistencyError::UnrelatedSeals);
+        }
+
+        let genesis_id = self.genesis.id();
+        let mut path = BTreeSet::new();
+        while let Some(opid) = queue.pop() {
+            if opid == genesis_id || !path.insert(opid) {
+                continue;
+            }
+            if let Some((_, transition)) = transitions.get(&opid) {
+                queue.extend(transition.inputs().iter().map(|input| input.prev_out.op));
+            } else if let Some(extension) = extensions.get(&opid) {
+                queue.extend(extension.redeemed.values().copied());
+            } else {
+                return Err(ConsistencyError::UnknownOperation(opid));
+            }
+        }
+
+        let mut consignment = self.clone();
+        consignment.terminals =
+            SmallOrdMap::from_iter_unsafe(terminals.into_iter().map(|(bundle_id, seals)| {
+                (bundle_id, Terminal {
+                    seals: SmallOrdSet::from_collection_unsafe(seals),
+                })
+            }));
+        let bundles = self.bundles.iter().cloned().filter_map(|mut bw| {
+            let mut known = false;
+            for bundle in bw.anchored_bundles.bundles_mut() {
+                let transitions = bundle
+                    .known_transitions
+                    .iter()
+                    .filter(|(opid, _)| path.contains(*opid))
+                    .map(|(opid, transition)| (*opid, transition.clone()))
+                    .collect::<BTreeMap<_, _>>();
+                // Bundle sharing anchor with a relevant one is kept intact,
+                // since the anchor commits to both of them
+                if !transitions.is_empty() {
+                    known = true;
+                    bundle.known_transitions = Confined::from_collection_unsafe(transitions);
+                }
+            }
+            known.then_some(bw)
+        });
+        consignment.bundles = LargeOrdSet::from_iter_unsafe(bundles);
+        consignment.extensions = LargeOrdSet::from_iter_unsafe(
+            self.extensions
+                .iter()
+                .filter(|extension| path.contains(&extension.id()))
+                .cloned(),
+        );
+
+        Ok(consignment)
+    }
+
+    /// Extracts minimal consignment for the recipient of the provided seals.
+    ///
+    /// Works like [`Consignment::subgraph_for`], dropping all operations not
+    /// on the path from the seals to the genesis, and then finalizes the
+    /// result, concealing all seals except the provided ones (see
+    /// [`Consignment::finalize`]).
+    ///
+    /// # Errors
+    ///
+    /// The same as for [`Consignment::subgraph_for`].
+    pub fn extract_for<'seal>(
+        &self,
+        seals: impl IntoIterator<Item = &'seal XChain<GraphSeal>>,
+    ) -> Result<Self, ConsistencyError> {
+        let seals = seals.into_iter().collect::<Vec<_>>();
+        let mut consignment = self.subgraph_for(seals.iter().copied())?;
+        let expose = seals
+            .into_iter()
+            .map(|seal| seal.map(TerminalSeal::conceal_from))
+            .collect();
+        consignment.finalize(&expose);
+        Ok(consignment)
+    }
+
+    /// Splits consignment paying multiple recipients into separate
+    /// consignments, one per each terminal seal. Each of them contains only
+    /// the history of the terminal seal and is finalized, concealing the
+    /// seals of all other recipients.
+    ///
+    /// # Errors
+    ///
+    /// If some terminal seal is not assigned by the consignment transitions,
+    /// or if the history of a terminal seal is incomplete.
+    pub fn split_per_terminal(
+        &self,
+    ) -> Result<BTreeMap<XChain<TerminalSeal>, Self>, ConsistencyError> {
+        let mut split = BTreeMap::new();
+        for seal in self.terminal_seals() {
+            let mut consignment = self.subgraph_inner(&bmap! { seal.conceal() => seal })?;
+            consignment.finalize(&bset! { seal });
+            split.insert(seal, consignment);
+        }
+        Ok(split)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use bp::seals::txout::CloseMethod;
+    use rgb::{AssignmentType, Opout};
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+    use crate::containers::consignment::test::{
+        bundle_id, bundled_witness, transfer, transition, with_seals,
+    };
+    use crate::containers::Transfer;
+
+    #[test]
+    fn subgraph_for() {
+        let ty = AssignmentType::with(1);
+        let seal = GraphSeal::with_blinded_vout(CloseMethod::OpretFirst, 0u32, 1);
+        let other = GraphSeal::with_blinded_vout(CloseMethod::OpretFirst, 1u32, 2);
+        let genesis_id = Transfer::strict_dumb().genesis.id();
+        let first = transition(1, [Opout::new(genesis_id, ty, 0)]);
+        let second = with_seals(transition(2, [Opout::new(first.id(), ty, 0)]), [seal]);
+        let unrelated = with_seals(transition(3, [Opout::new(genesis_id, ty, 1)]), [other]);
+        let (first_id, second_id) = (first.id(), second.id());
+        let witness = bundled_witness(2, [second.clone()]);
+        let bundle_id = bundle_id(&witness);
+
+        let history =
+            transfer([bundled_witness(1, [first]), witness, bundled_witness(3, [unrelated])]);
+        let seal = XChain::Bitcoin(seal);
+        let subgraph = history.subgraph_for([&seal]).unwrap();
+        assert_eq!(subgraph.genesis, history.genesis);
+        assert_eq!(
+            subgraph
+                .operations()
+                .into_iter()
+                .map(|op| op.opid())
+                .collect::<BTreeSet<_>>(),
+            bset![genesis_id, first_id, second_id]
+        );
+        assert_eq!(subgraph.terminals.len(), 1);
+        assert_eq!(
+            subgraph.terminals.get(&bundle_id).unwrap().seals,
+            SmallOrdSet::try_from_iter([seal.map(TerminalSeal::conceal_from)]).unwrap()
+        );
+
+        let unknown =
+            XChain::Bitcoin(GraphSeal::with_blinded_vout(CloseMethod::OpretFirst, 2u32, 3));
+        assert!(matches!(history.subgraph_for([&unknown]), Err(ConsistencyError::UnrelatedSeals)));
+
+        let incomplete = transfer([bundled_witness(2, [second])]);
+        assert!(matches!(
+            incomplete.subgraph_for([&seal]),
+            Err(ConsistencyError::UnknownOperation(opid)) if opid == first_id
+        ));
+    }
+
+    #[test]
+    fn extract_for() {
+        let ty = AssignmentType::with(1);
+        let seal = GraphSeal::with_blinded_vout(CloseMethod::OpretFirst, 0u32, 1);
+        let change = GraphSeal::with_blinded_vout(CloseMethod::OpretFirst, 1u32, 2);
+        let other = GraphSeal::with_blinded_vout(CloseMethod::OpretFirst, 2u32, 3);
+        let genesis_id = Transfer::strict_dumb().genesis.id();
+        let first = transition(1, [Opout::new(genesis_id, ty, 0)]);
+        let second = with_seals(transition(2, [Opout::new(first.id(), ty, 0)]), [seal, change]);
+        let unrelated = with_seals(transition(3, [Opout::new(genesis_id, ty, 1)]), [other]);
+        let (first_id, second_id) = (first.id(), second.id());
+
+        let history = transfer([
+            bundled_witness(1, [first]),
+            bundled_witness(2, [second]),
+            bundled_witness(3, [unrelated]),
+        ]);
+        let seal = XChain::Bitcoin(seal);
+        assert!(history
+            .subgraph_for([&seal])
+            .unwrap()
+            .check_finalized()
+            .is_err());
+
+        let extracted = history.extract_for([&seal]).unwrap();
+        assert_eq!(
+            extracted
+                .operations()
+                .into_iter()
+                .map(|op| op.opid())
+                .collect::<BTreeSet<_>>(),
+            bset![genesis_id, first_id, second_id]
+        );
+        assert_eq!(extracted.terminal_seals().collect::<Vec<_>>(), vec![
+            seal.map(TerminalSeal::conceal_from)
+        ]);
+        assert!(extracted.check_finalized().is_ok());
+    }
+
+    #[test]
+    fn split_per_terminal() {
+        let ty = AssignmentType::with(1);
+        let alice = GraphSeal::with_blinded_vout(CloseMethod::OpretFirst, 0u32, 1);
+        let bob = GraphSeal::with_blinded_vout(CloseMethod::OpretFirst, 1u32, 2);
+        let genesis_id = Transfer::strict_dumb().genesis.id();
+        let first = with_seals(transition(1, [Opout::new(genesis_id, ty, 0)]), [alice]);
+        let second = with_seals(transition(2, [Opout::new(genesis_id, ty, 1)]), [bob]);
+        let (first_id, second_id) = (first.id(), second.id());
+
+        let mut transfer = transfer([bundled_witness(1, [first, second])]);
+        let bundle_id = bundle_id(transfer.bundles.first().unwrap());
+        let alice = XChain::Bitcoin(alice).map(TerminalSeal::conceal_from);
+        let bob = XChain::Bitcoin(bob).map(TerminalSeal::conceal_from);
+        transfer.terminals = SmallOrdMap::try_from_iter([(bundle_id, Terminal {
+            seals: SmallOrdSet::try_from_iter([alice, bob]).unwrap(),
+        })])
+        .unwrap();
+
+        let split = transfer.split_per_terminal().unwrap();
+        assert_eq!(split.len(), 2);
+        for (seal, opid) in [(alice, first_id), (bob, second_id)] {
+            let consignment = &split[&seal];
+            assert_eq!(consignment.terminal_seals().collect::<Vec<_>>(), vec![seal]);
+            assert!(consignment.check_finalized().is_ok());
+            assert_eq!(
+                consignment
+                    .operations()
+                    .into_iter()
+                    .map(|op| op.opid())
+                    .collect::<BTreeSet<_>>(),
+                bset![genesis_id, opid]
+            );
+        }
+    }
+}
@@ -0,0 +1,180 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeSet;
+use std::ops::Deref;
+
+use rgb::{validation, XChain};
+
+use super::{Consignment, ConsistencyError, PolicyReport, TerminalSeal};
+
+pub type ValidContract = ValidConsignment<false>;
+pub type ValidTransfer = ValidConsignment<true>;
+
+#[derive(Clone, Debug, Display)]
+#[display("{consignment}")]
+pub struct ValidConsignment<const TRANSFER: bool> {
+    /// Status of the latest validation.
+    pub(super) validation_status: validation::Status,
+    /// Typed results of the policy checks of the latest validation.
+    pub(super) policy_report: PolicyReport,
+    pub(super) consignment: Consignment<TRANSFER>,
+}
+
+impl<const TRANSFER: bool> ValidConsignment<TRANSFER> {
+    pub fn validation_status(&self) -> &validation::Status { &self.validation_status }
+
+    pub fn policy_report(&self) -> &PolicyReport { &self.policy_report }
+
+    pub fn into_consignment(self) -> Consignment<TRANSFER> { self.consignment }
+
+    pub fn into_validation_status(self) -> validation::Status { self.validation_status }
+
+    pub fn split(self) -> (Consignment<TRANSFER>, validation::Status) {
+        (self.consignment, self.validation_status)
+    }
+}
+
+impl<const TRANSFER: bool> Deref for ValidConsignment<TRANSFER> {
+    type Target = Consignment<TRANSFER>;
+
+    fn deref(&self) -> &Self::Target { &self.consignment }
+}
+
+/// Consignment which is being prepared to be sent to a counterparty and may
+/// contain revealed seals not intended for the receiver.
+#[derive(Clone, Debug, Display)]
+#[display("{0}")]
+pub struct OutboundConsignment<const TRANSFER: bool>(Consignment<TRANSFER>);
+
+impl<const TRANSFER: bool> OutboundConsignment<TRANSFER> {
+    pub fn new(consignment: Consignment<TRANSFER>) -> Self { Self(consignment) }
+
+    pub fn as_draft_mut(&mut self) -> &mut Consignment<TRANSFER> { &mut self.0 }
+
+    /// Finalizes the consignment (see [`Consignment::finalize`]), producing
+    /// data which can be sent to the counterparty.
+    pub fn finalize(mut self, expose: &BTreeSet<XChain<TerminalSeal>>) -> Consignment<TRANSFER> {
+        self.0.finalize(expose);
+        self.0
+    }
+}
+
+impl<const TRANSFER: bool> Deref for OutboundConsignment<TRANSFER> {
+    type Target = Consignment<TRANSFER>;
+
+    fn deref(&self) -> &Self::Target { &self.0 }
+}
+
+/// Consignment received from a counterparty, which is guaranteed to be
+/// finalized and to have connected history.
+#[derive(Clone, Debug, Display)]
+#[display("{0}")]
+pub struct InboundConsignment<const TRANSFER: bool>(Consignment<TRANSFER>);
+
+impl<const TRANSFER: bool> InboundConsignment<TRANSFER> {
+    /// Accepts consignment received from a counterparty.
+    ///
+    /// # Errors
+    ///
+    /// If the consignment fails [`Consignment::check_connectivity`] or
+    /// [`Consignment::check_finalized`].
+    pub fn accept(consignment: Consignment<TRANSFER>) -> Result<Self, ConsistencyError> {
+        consignment.check_connectivity()?;
+        consignment.check_finalized()?;
+        Ok(Self(consignment))
+    }
+
+    pub fn into_consignment(self) -> Consignment<TRANSFER> { self.0 }
+
+    /// Converts the consignment into an outbound one, for instance to forward
+    /// it to another party.
+    pub fn into_outbound(self) -> OutboundConsignment<TRANSFER> { OutboundConsignment(self.0) }
+}
+
+impl<const TRANSFER: bool> Deref for InboundConsignment<TRANSFER> {
+    type Target = Consignment<TRANSFER>;
+
+    fn deref(&self) -> &Self::Target { &self.0 }
+}
+
+#[cfg(test)]
+mod test {
+
+    use amplify::confinement::{SmallOrdMap, SmallOrdSet};
+    use amplify::ByteArray;
+    use rgb::{AssignmentType, OpId, Operation, Opout};
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+    use crate::containers::consignment::test::{
+        bundle_id, bundled_witness, transfer, transition, with_seals,
+    };
+    use crate::containers::{Terminal, Transfer, VoutSeal};
+
+    #[test]
+    fn inbound_outbound() {
+        let kept = VoutSeal::with_opret(0u32, 1);
+        let change = VoutSeal::with_opret(1u32, 2);
+        let genesis_id = Transfer::strict_dumb().genesis.id();
+        let assigning =
+            with_seals(transition(1, [Opout::new(genesis_id, AssignmentType::with(1), 0)]), [
+                kept.into(),
+                change.into(),
+            ]);
+        let opid = assigning.id();
+        let witness = bundled_witness(1, [assigning]);
+        let bundle_id = bundle_id(&witness);
+
+        let kept = XChain::Bitcoin(TerminalSeal::from(kept));
+        let mut draft = transfer([witness]);
+        draft.terminals = SmallOrdMap::try_from_iter([(bundle_id, Terminal {
+            seals: SmallOrdSet::try_from_iter([kept]).unwrap(),
+        })])
+        .unwrap();
+        assert_eq!(
+            InboundConsignment::accept(draft.clone()).unwrap_err(),
+            ConsistencyError::NotFinalized(opid)
+        );
+
+        let sendable = OutboundConsignment::new(draft).finalize(&bset![kept]);
+        let inbound = InboundConsignment::accept(sendable.clone()).unwrap();
+        assert_eq!(inbound.into_outbound().finalize(&bset![kept]), sendable);
+
+        let mut dangling = sendable.clone();
+        dangling.bundles = none!();
+        assert_eq!(
+            InboundConsignment::accept(dangling).unwrap_err(),
+            ConsistencyError::DanglingTerminal(bundle_id)
+        );
+
+        let unknown = OpId::from_byte_array([0xAA; 32]);
+        let disconnected = transfer([bundled_witness(2, [transition(1, [Opout::new(
+            unknown,
+            AssignmentType::with(1),
+            0,
+        )])])]);
+        assert_eq!(
+            InboundConsignment::accept(disconnected).unwrap_err(),
+            ConsistencyError::UnknownOperation(unknown)
+        );
+    }
+}
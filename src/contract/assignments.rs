@@ -19,17 +19,28 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::BTreeSet;
+
 use amplify::confinement::SmallVec;
 use commit_verify::Conceal;
 use rgb::{
     Assign, AssignAttach, AssignData, AssignFungible, AssignRights, ExposedSeal, ExposedState,
-    TypedAssigns, XChain,
+    SecretSeal, TypedAssigns, XChain,
 };
 
 pub trait TypedAssignsExt<Seal: ExposedSeal> {
     fn reveal_seal(&mut self, seal: XChain<Seal>);
 
     fn filter_revealed_seals(&self) -> Vec<XChain<Seal>>;
+
+    /// Conceals revealed seals of all assignments, except the ones which
+    /// concealed form is present in `except` set. The assigned state is kept
+    /// as is.
+    ///
+    /// # Returns
+    ///
+    /// Number of assignments which seals were concealed.
+    fn conceal_seals_except(&mut self, except: &BTreeSet<XChain<SecretSeal>>) -> usize;
 }
 
 impl<Seal: ExposedSeal> TypedAssignsExt<Seal> for TypedAssigns<Seal> {
@@ -69,6 +80,45 @@ impl<Seal: ExposedSeal> TypedAssignsExt<Seal> for TypedAssigns<Seal> {
         }
     }
 
+    fn conceal_seals_except(&mut self, except: &BTreeSet<XChain<SecretSeal>>) -> usize {
+        fn conceal<State: ExposedState, Seal: ExposedSeal>(
+            vec: &mut SmallVec<Assign<State, Seal>>,
+            except: &BTreeSet<XChain<SecretSeal>>,
+        ) -> usize {
+            let mut count = 0;
+            for assign in vec.iter_mut() {
+                match assign {
+                    Assign::Revealed { seal, state, lock } if !except.contains(&seal.conceal()) => {
+                        *assign = Assign::ConfidentialSeal {
+                            seal: seal.conceal(),
+                            state: state.clone(),
+                            lock: *lock,
+                        }
+                    }
+                    Assign::ConfidentialState { seal, state, lock }
+                        if !except.contains(&seal.conceal()) =>
+                    {
+                        *assign = Assign::Confidential {
+                            seal: seal.conceal(),
+                            state: *state,
+                            lock: *lock,
+                        }
+                    }
+                    _ => continue,
+                }
+                count += 1;
+            }
+            count
+        }
+
+        match self {
+            TypedAssigns::Declarative(v) => conceal(v, except),
+            TypedAssigns::Fungible(v) => conceal(v, except),
+            TypedAssigns::Structured(v) => conceal(v, except),
+            TypedAssigns::Attachment(v) => conceal(v, except),
+        }
+    }
+
     fn filter_revealed_seals(&self) -> Vec<XChain<Seal>> {
         match self {
             TypedAssigns::Declarative(s) => {
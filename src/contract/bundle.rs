@@ -19,9 +19,17 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use rgb::{GraphSeal, OpId, Operation, Transition, TransitionBundle, XChain};
+use std::collections::{BTreeMap, BTreeSet};
+use std::vec;
 
-use crate::contract::TypedAssignsExt;
+use amplify::confinement::Confined;
+use bp::seals::txout::CloseMethod;
+use rgb::{
+    ContractId, GraphSeal, InputMap, OpId, Operation, Transition, TransitionBundle, Vin, XChain,
+};
+use strict_encoding::{StrictEncode, StrictWriter};
+
+use crate::contract::{MergeReveal, MergeRevealError, TypedAssignsExt};
 
 #[derive(Clone, Eq, PartialEq, Debug, Display, Error)]
 #[display(doc_comments)]
@@ -30,7 +38,50 @@ pub enum RevealError {
     UnrelatedTransition(OpId, Transition),
 }
 
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum AggregateError {
+    /// bundles use different seal closing methods {0} and {1}.
+    CloseMethodMismatch(CloseMethod, CloseMethod),
+
+    /// bundles contain state transitions of different contracts {0} and {1}.
+    ContractMismatch(ContractId, ContractId),
+
+    /// state transition present in both bundles can't be merged: {0}
+    #[from]
+    MergeReveal(MergeRevealError),
+
+    /// witness transaction input {0} is spent by two distinct state
+    /// transitions {1} and {2}.
+    InputConflict(Vin, OpId, OpId),
+
+    /// aggregated bundle contains too many state transitions.
+    TooLarge,
+}
+
+/// Entry of a transition bundle, which may be either revealed or concealed.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum BundleEntry<'bundle> {
+    /// State transition known to the bundle, together with the bundle inputs
+    /// it spends.
+    Revealed {
+        transition: &'bundle Transition,
+        inputs: BTreeSet<Vin>,
+    },
+
+    /// State transition which is present in the bundle input map, but is not
+    /// revealed.
+    Concealed { opid: OpId },
+}
+
 pub trait BundleExt {
+    /// Iterates over all state transitions referenced by the bundle input map,
+    /// yielding revealed and concealed transitions as distinct entries.
+    fn iter_entries(&self) -> vec::IntoIter<BundleEntry<'_>>;
+
+    /// Returns length of the strict-encoded bundle data, in bytes.
+    fn strict_weight(&self) -> usize;
+
     /// Ensures that the seal is revealed inside the bundle.
     fn reveal_seal(&mut self, seal: XChain<GraphSeal>);
 
@@ -41,9 +92,43 @@ pub trait BundleExt {
     /// `true` if the transition was previously concealed; `false` if it was
     /// already revealed; error if the transition is unrelated to the bundle.
     fn reveal_transition(&mut self, transition: Transition) -> Result<bool, RevealError>;
+
+    /// Aggregates two bundles of the same contract, which were composed
+    /// independently but are anchored to the same witness transaction, into a
+    /// single bundle.
+    ///
+    /// # Errors
+    ///
+    /// If the bundles use different close methods or belong to different
+    /// contracts, or if the same witness transaction input is spent by
+    /// different state transitions.
+    fn aggregate(self, other: TransitionBundle) -> Result<TransitionBundle, AggregateError>;
 }
 
 impl BundleExt for TransitionBundle {
+    fn iter_entries(&self) -> vec::IntoIter<BundleEntry<'_>> {
+        let mut inputs = BTreeMap::<OpId, BTreeSet<Vin>>::new();
+        for (vin, opid) in &self.input_map {
+            inputs.entry(*opid).or_default().insert(*vin);
+        }
+        inputs
+            .into_iter()
+            .map(|(opid, inputs)| match self.known_transitions.get(&opid) {
+                Some(transition) => BundleEntry::Revealed { transition, inputs },
+                None => BundleEntry::Concealed { opid },
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    fn strict_weight(&self) -> usize {
+        self.strict_encode(StrictWriter::counter::<{ usize::MAX }>())
+            .expect("counter writer is unlimited")
+            .unbox()
+            .unconfine()
+            .count
+    }
+
     fn reveal_seal(&mut self, seal: XChain<GraphSeal>) {
         for (_, transition) in self.known_transitions.keyed_values_mut() {
             for (_, assign) in transition.assignments.keyed_values_mut() {
@@ -65,4 +150,251 @@ impl BundleExt for TransitionBundle {
             .expect("same size as input map");
         Ok(true)
     }
+
+    fn aggregate(self, other: TransitionBundle) -> Result<TransitionBundle, AggregateError> {
+        if self.close_method != other.close_method {
+            return Err(AggregateError::CloseMethodMismatch(self.close_method, other.close_method));
+        }
+        // Bundles always reveal at least one transition, so each of them is
+        // checked against the contract
+        let mut contract_id = None;
+        for bundle in [&self, &other] {
+            for transition in bundle.known_transitions.values() {
+                let id = *contract_id.get_or_insert(transition.contract_id);
+                if id != transition.contract_id {
+                    return Err(AggregateError::ContractMismatch(id, transition.contract_id));
+                }
+            }
+        }
+
+        let mut input_map = self
+            .input_map
+            .iter()
+            .map(|(vin, opid)| (*vin, *opid))
+            .collect::<BTreeMap<_, _>>();
+        for (vin, opid) in &other.input_map {
+            match input_map.insert(*vin, *opid) {
+                Some(prev) if prev != *opid => {
+                    return Err(AggregateError::InputConflict(*vin, prev, *opid));
+                }
+                _ => {}
+            }
+        }
+        let mut known_transitions = self.known_transitions.into_inner();
+        for (opid, transition) in other.known_transitions {
+            let transition = match known_transitions.remove(&opid) {
+                Some(known) => known.merge_reveal(transition)?,
+                None => transition,
+            };
+            known_transitions.insert(opid, transition);
+        }
+
+        Ok(TransitionBundle {
+            close_method: self.close_method,
+            input_map: InputMap::from(
+                Confined::try_from(input_map).map_err(|_| AggregateError::TooLarge)?,
+            ),
+            known_transitions: Confined::try_from(known_transitions)
+                .map_err(|_| AggregateError::TooLarge)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::confinement::SmallVec;
+    use amplify::{ByteArray, Wrapper};
+    use commit_verify::Conceal;
+    use rgb::{Assign, AssignmentType, Assignments, TransitionType, TypedAssigns, VoidState};
+    use strict_encoding::{StrictDumb, StrictWriter};
+
+    use super::*;
+
+    fn transition(ty: u16) -> Transition {
+        let mut transition = Transition::strict_dumb();
+        transition.transition_type = TransitionType::with(ty);
+        transition
+    }
+
+    #[test]
+    fn iter_entries() {
+        let first = transition(1);
+        let second = transition(2);
+        let concealed = [transition(3).id(), transition(4).id()];
+
+        let bundle = TransitionBundle {
+            close_method: CloseMethod::OpretFirst,
+            input_map: InputMap::from(
+                Confined::try_from(BTreeMap::from([
+                    (Vin::from_u32(0), first.id()),
+                    (Vin::from_u32(1), first.id()),
+                    (Vin::from_u32(2), second.id()),
+                    (Vin::from_u32(3), concealed[0]),
+                    (Vin::from_u32(4), concealed[1]),
+                ]))
+                .unwrap(),
+            ),
+            known_transitions: Confined::try_from(BTreeMap::from([
+                (first.id(), first.clone()),
+                (second.id(), second.clone()),
+            ]))
+            .unwrap(),
+        };
+
+        let entries = bundle.iter_entries().collect::<Vec<_>>();
+        assert_eq!(entries.len(), 4);
+        let revealed = entries
+            .iter()
+            .filter(|entry| matches!(entry, BundleEntry::Revealed { .. }))
+            .count();
+        assert_eq!(revealed, 2);
+        assert!(entries.contains(&BundleEntry::Revealed {
+            transition: &first,
+            inputs: bset![Vin::from_u32(0), Vin::from_u32(1)],
+        }));
+        assert!(entries.contains(&BundleEntry::Revealed {
+            transition: &second,
+            inputs: bset![Vin::from_u32(2)],
+        }));
+        for opid in concealed {
+            assert!(entries.contains(&BundleEntry::Concealed { opid }));
+        }
+    }
+
+    #[test]
+    fn strict_weight() {
+        let first = transition(1);
+        let bundle = TransitionBundle {
+            close_method: CloseMethod::OpretFirst,
+            input_map: InputMap::from(
+                Confined::try_from(BTreeMap::from([(Vin::from_u32(0), first.id())])).unwrap(),
+            ),
+            known_transitions: Confined::try_from(BTreeMap::from([(first.id(), first)])).unwrap(),
+        };
+        let data = bundle
+            .strict_encode(StrictWriter::in_memory::<{ usize::MAX }>())
+            .unwrap()
+            .unbox()
+            .unconfine();
+        assert_eq!(bundle.strict_weight(), data.len());
+    }
+
+    #[test]
+    fn aggregate() {
+        let bundle = |close_method, inputs: &[(u32, &Transition)]| TransitionBundle {
+            close_method,
+            input_map: InputMap::from(
+                Confined::try_from_iter(
+                    inputs
+                        .iter()
+                        .map(|(vin, transition)| (Vin::from_u32(*vin), transition.id())),
+                )
+                .unwrap(),
+            ),
+            known_transitions: Confined::try_from_iter(
+                inputs
+                    .iter()
+                    .map(|(_, transition)| (transition.id(), (*transition).clone())),
+            )
+            .unwrap(),
+        };
+        let first = transition(1);
+        let second = transition(2);
+
+        let aggregated = bundle(CloseMethod::OpretFirst, &[(0, &first), (1, &first)])
+            .aggregate(bundle(CloseMethod::OpretFirst, &[(2, &second)]))
+            .unwrap();
+        assert_eq!(
+            aggregated,
+            bundle(CloseMethod::OpretFirst, &[(0, &first), (1, &first), (2, &second)])
+        );
+
+        assert_eq!(
+            bundle(CloseMethod::OpretFirst, &[(0, &first)])
+                .aggregate(bundle(CloseMethod::TapretFirst, &[(1, &second)])),
+            Err(AggregateError::CloseMethodMismatch(
+                CloseMethod::OpretFirst,
+                CloseMethod::TapretFirst
+            ))
+        );
+        assert_eq!(
+            bundle(CloseMethod::OpretFirst, &[(0, &first)])
+                .aggregate(bundle(CloseMethod::OpretFirst, &[(0, &second)])),
+            Err(AggregateError::InputConflict(Vin::from_u32(0), first.id(), second.id()))
+        );
+
+        let mut other = transition(3);
+        other.contract_id = ContractId::from_byte_array([1; 32]);
+        assert_eq!(
+            bundle(CloseMethod::OpretFirst, &[(0, &first)])
+                .aggregate(bundle(CloseMethod::OpretFirst, &[(1, &other)])),
+            Err(AggregateError::ContractMismatch(first.contract_id, other.contract_id))
+        );
+        assert_eq!(
+            bundle(CloseMethod::OpretFirst, &[(0, &first)])
+                .aggregate(bundle(CloseMethod::OpretFirst, &[(1, &second), (2, &other)])),
+            Err(AggregateError::ContractMismatch(first.contract_id, other.contract_id))
+        );
+    }
+
+    #[test]
+    fn aggregate_merge_reveal() {
+        let mut first = transition(1);
+        first.assignments = Assignments::from_inner(
+            Confined::try_from(BTreeMap::from([(
+                AssignmentType::with(1),
+                TypedAssigns::Declarative(
+                    SmallVec::try_from_iter([Assign::revealed(
+                        XChain::Bitcoin(GraphSeal::with_blinded_vout(
+                            CloseMethod::OpretFirst,
+                            0,
+                            0,
+                        )),
+                        VoidState::default(),
+                    )])
+                    .unwrap(),
+                ),
+            )]))
+            .unwrap(),
+        );
+        let concealed = first.conceal();
+        let revealed_seal = |bundle: &TransitionBundle| {
+            bundle.known_transitions[&first.id()]
+                .assignments
+                .get(&AssignmentType::with(1))
+                .unwrap()
+                .revealed_seal_at(0)
+                .unwrap()
+        };
+        let second = transition(2);
+        let bundle = |inputs: &[(u32, &Transition)]| TransitionBundle {
+            close_method: CloseMethod::OpretFirst,
+            input_map: InputMap::from(
+                Confined::try_from_iter(
+                    inputs
+                        .iter()
+                        .map(|(vin, transition)| (Vin::from_u32(*vin), transition.id())),
+                )
+                .unwrap(),
+            ),
+            known_transitions: Confined::try_from_iter(
+                inputs
+                    .iter()
+                    .map(|(_, transition)| (transition.id(), (*transition).clone())),
+            )
+            .unwrap(),
+        };
+
+        let expected = bundle(&[(0, &first), (1, &second)]);
+        assert!(revealed_seal(&expected).is_some());
+        assert!(revealed_seal(&bundle(&[(0, &concealed)])).is_none());
+        for aggregated in [
+            bundle(&[(0, &first)]).aggregate(bundle(&[(0, &concealed), (1, &second)])),
+            bundle(&[(0, &concealed), (1, &second)]).aggregate(bundle(&[(0, &first)])),
+        ] {
+            let aggregated = aggregated.unwrap();
+            assert_eq!(aggregated, expected);
+            assert_eq!(revealed_seal(&aggregated), revealed_seal(&expected));
+        }
+    }
 }
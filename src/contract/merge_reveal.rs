@@ -220,10 +220,11 @@ impl MergeReveal for TransitionBundle {
 
         let mut self_transitions = self.known_transitions.into_inner();
         for (opid, other_transition) in other.known_transitions {
-            if let Some(mut transition) = self_transitions.remove(&opid) {
-                transition = transition.merge_reveal(other_transition)?;
-                self_transitions.insert(opid, transition);
-            }
+            let transition = match self_transitions.remove(&opid) {
+                Some(transition) => transition.merge_reveal(other_transition)?,
+                None => other_transition,
+            };
+            self_transitions.insert(opid, transition);
         }
         self.known_transitions = Confined::from_collection_unsafe(self_transitions);
 
@@ -22,7 +22,9 @@
 mod assignments;
 mod bundle;
 mod merge_reveal;
+mod schema;
 
 pub use assignments::TypedAssignsExt;
-pub use bundle::{BundleExt, RevealError};
+pub use bundle::{AggregateError, BundleEntry, BundleExt, RevealError};
 pub use merge_reveal::{MergeReveal, MergeRevealError};
+pub use schema::{SchemaCompat, SchemaDiff, SchemaExt, SchemaLint, TypeDiff};
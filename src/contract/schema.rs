@@ -0,0 +1,566 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::hash::Hash;
+
+use amplify::confinement::TinyOrdMap;
+use rgb::{
+    AssignmentType, AssignmentsSchema, ExtensionSchema, ExtensionType, GlobalStateType, MetaType,
+    OpFullType, Schema, TransitionSchema, TransitionType, ValencyType,
+};
+
+/// Common mistakes of schema authoring detected by [`SchemaExt::lint`].
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display)]
+#[display(doc_comments)]
+pub enum SchemaLint {
+    /// metadata type {0} is declared by the schema, but is not used by any
+    /// operation.
+    UnusedMetaType(MetaType),
+
+    /// global state type {0} is declared by the schema, but is not used by any
+    /// operation.
+    UnusedGlobalType(GlobalStateType),
+
+    /// owned state type {0} is declared by the schema, but is never assigned by
+    /// any operation.
+    UnassignedOwnedType(AssignmentType),
+
+    /// valency type {0} is declared by the schema, but is not used by any
+    /// operation.
+    UnusedValencyType(ValencyType),
+
+    /// {0} uses metadata type {1}, which is not declared by the schema.
+    UndeclaredMetaType(OpFullType, MetaType),
+
+    /// {0} uses global state type {1}, which is not declared by the schema.
+    UndeclaredGlobalType(OpFullType, GlobalStateType),
+
+    /// {0} uses owned state type {1}, which is not declared by the schema.
+    UndeclaredOwnedType(OpFullType, AssignmentType),
+
+    /// {0} uses valency type {1}, which is not declared by the schema.
+    UndeclaredValencyType(OpFullType, ValencyType),
+
+    /// {0} defines impossible number of global state items of type {1}.
+    ImpossibleGlobalOccurrences(OpFullType, GlobalStateType),
+
+    /// {0} defines impossible number of owned state items of type {1}.
+    ImpossibleOwnedOccurrences(OpFullType, AssignmentType),
+
+    /// state transition {0} can't ever happen, since the state it requires as
+    /// inputs is never assigned by the genesis or other reachable operations.
+    UnreachableTransition(TransitionType),
+
+    /// state extension {0} can't ever happen, since the valencies it redeems
+    /// are never defined by the genesis or other reachable operations.
+    UnreachableExtension(ExtensionType),
+}
+
+/// Compatibility of two versions of a schema, as reported by
+/// [`SchemaDiff::compatibility`].
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display)]
+#[display(lowercase)]
+pub enum SchemaCompat {
+    /// Schemata define the same state and operations.
+    Identical,
+
+    /// Schemata define the same state, operations and validation scripts, but
+    /// differ in descriptive information, format version or flags, and thus
+    /// have different ids.
+    WireCompatible,
+
+    /// The new schema only adds state types and operations, keeping all
+    /// existing ones intact, so the state of the existing contracts stays
+    /// valid.
+    StateCompatible,
+
+    /// Some of the state types or operations were removed or changed, or the
+    /// validation scripts were changed, which may invalidate existing state.
+    Breaking,
+}
+
+/// Changes of a single kind of schema definitions.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct TypeDiff<T: Ord> {
+    pub added: BTreeSet<T>,
+    pub removed: BTreeSet<T>,
+    pub changed: BTreeSet<T>,
+}
+
+impl<T: Ord> Default for TypeDiff<T> {
+    fn default() -> Self {
+        TypeDiff {
+            added: empty!(),
+            removed: empty!(),
+            changed: empty!(),
+        }
+    }
+}
+
+impl<T: Ord + Copy> TypeDiff<T> {
+    fn with<V: Eq>(old: &BTreeMap<T, V>, new: &BTreeMap<T, V>) -> Self {
+        let mut diff = Self::default();
+        for (ty, def) in old {
+            match new.get(ty) {
+                None => {
+                    diff.removed.insert(*ty);
+                }
+                Some(new_def) if new_def != def => {
+                    diff.changed.insert(*ty);
+                }
+                Some(_) => {}
+            }
+        }
+        diff.added
+            .extend(new.keys().filter(|ty| !old.contains_key(*ty)).copied());
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    fn is_breaking(&self) -> bool { !self.removed.is_empty() || !self.changed.is_empty() }
+}
+
+/// Differences between two versions of a schema, produced by
+/// [`SchemaExt::diff`].
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct SchemaDiff {
+    /// Whether schema name, developer or timestamp differ.
+    pub info_changed: bool,
+    /// Whether schema format version, flags or reserved bytes differ.
+    pub format_changed: bool,
+    pub meta_types: TypeDiff<MetaType>,
+    pub global_types: TypeDiff<GlobalStateType>,
+    pub owned_types: TypeDiff<AssignmentType>,
+    pub valency_types: TypeDiff<ValencyType>,
+    /// Whether genesis definition (ignoring its validation script) differs.
+    pub genesis_changed: bool,
+    pub transitions: TypeDiff<TransitionType>,
+    pub extensions: TypeDiff<ExtensionType>,
+    /// Operations present in both schemata which validation scripts differ.
+    pub validators: BTreeSet<OpFullType>,
+}
+
+impl SchemaDiff {
+    pub fn is_empty(&self) -> bool { self.compatibility() == SchemaCompat::Identical }
+
+    /// Detects compatibility verdict for the differences.
+    pub fn compatibility(&self) -> SchemaCompat {
+        let breaking = self.genesis_changed ||
+            self.meta_types.is_breaking() ||
+            self.global_types.is_breaking() ||
+            self.owned_types.is_breaking() ||
+            self.valency_types.is_breaking() ||
+            self.transitions.is_breaking() ||
+            self.extensions.is_breaking();
+        let added = !self.meta_types.added.is_empty() ||
+            !self.global_types.added.is_empty() ||
+            !self.owned_types.added.is_empty() ||
+            !self.valency_types.added.is_empty() ||
+            !self.transitions.added.is_empty() ||
+            !self.extensions.added.is_empty();
+
+        // Changed validation scripts may invalidate existing state
+        if breaking || !self.validators.is_empty() {
+            SchemaCompat::Breaking
+        } else if added {
+            SchemaCompat::StateCompatible
+        } else if self.info_changed || self.format_changed {
+            SchemaCompat::WireCompatible
+        } else {
+            SchemaCompat::Identical
+        }
+    }
+}
+
+pub trait SchemaExt {
+    /// Checks schema for common authoring mistakes which are not consensus
+    /// violations, but make some parts of the schema useless.
+    fn lint(&self) -> Vec<SchemaLint>;
+
+    /// Reports differences between this and the `other` (newer) version of
+    /// the schema.
+    fn diff(&self, other: &Schema) -> SchemaDiff;
+}
+
+impl SchemaExt for Schema {
+    fn lint(&self) -> Vec<SchemaLint> {
+        let mut lints = vec![];
+
+        let mut ops = vec![(
+            OpFullType::Genesis,
+            &self.genesis.metadata,
+            &self.genesis.globals,
+            &self.genesis.assignments,
+            &self.genesis.valencies,
+        )];
+        for (ty, schema) in &self.transitions {
+            ops.push((
+                OpFullType::StateTransition(*ty),
+                &schema.metadata,
+                &schema.globals,
+                &schema.assignments,
+                &schema.valencies,
+            ));
+        }
+        for (ty, schema) in &self.extensions {
+            ops.push((
+                OpFullType::StateExtension(*ty),
+                &schema.metadata,
+                &schema.globals,
+                &schema.assignments,
+                &schema.valencies,
+            ));
+        }
+
+        let mut used_meta = BTreeSet::new();
+        let mut used_globals = BTreeSet::new();
+        let mut assigned = BTreeSet::new();
+        let mut used_valencies = BTreeSet::new();
+        for (op, metadata, globals, assignments, valencies) in &ops {
+            let op = *op;
+            for ty in *metadata {
+                if !self.meta_types.contains_key(ty) {
+                    lints.push(SchemaLint::UndeclaredMetaType(op, *ty));
+                }
+                used_meta.insert(*ty);
+            }
+            for (ty, occ) in *globals {
+                if !self.global_types.contains_key(ty) {
+                    lints.push(SchemaLint::UndeclaredGlobalType(op, *ty));
+                }
+                if occ.max_value() == 0 || occ.min_value() > occ.max_value() {
+                    lints.push(SchemaLint::ImpossibleGlobalOccurrences(op, *ty));
+                }
+                used_globals.insert(*ty);
+            }
+            lint_owned(self, op, assignments, &mut lints);
+            assigned.extend(assignments.keys().copied());
+            for ty in *valencies {
+                if !self.valency_types.contains(ty) {
+                    lints.push(SchemaLint::UndeclaredValencyType(op, *ty));
+                }
+                used_valencies.insert(*ty);
+            }
+        }
+        for (ty, schema) in &self.transitions {
+            lint_owned(self, OpFullType::StateTransition(*ty), &schema.inputs, &mut lints);
+        }
+        for (ty, schema) in &self.extensions {
+            for valency in &schema.redeems {
+                if !self.valency_types.contains(valency) {
+                    lints.push(SchemaLint::UndeclaredValencyType(
+                        OpFullType::StateExtension(*ty),
+                        *valency,
+                    ));
+                }
+                used_valencies.insert(*valency);
+            }
+        }
+
+        lints.extend(
+            self.meta_types
+                .keys()
+                .filter(|ty| !used_meta.contains(*ty))
+                .map(|ty| SchemaLint::UnusedMetaType(*ty)),
+        );
+        lints.extend(
+            self.global_types
+                .keys()
+                .filter(|ty| !used_globals.contains(*ty))
+                .map(|ty| SchemaLint::UnusedGlobalType(*ty)),
+        );
+        lints.extend(
+            self.owned_types
+                .keys()
+                .filter(|ty| !assigned.contains(*ty))
+                .map(|ty| SchemaLint::UnassignedOwnedType(*ty)),
+        );
+        lints.extend(
+            self.valency_types
+                .iter()
+                .filter(|ty| !used_valencies.contains(*ty))
+                .map(|ty| SchemaLint::UnusedValencyType(*ty)),
+        );
+
+        // Find operations reachable from genesis
+        let mut reachable_assignments = self
+            .genesis
+            .assignments
+            .keys()
+            .copied()
+            .collect::<BTreeSet<_>>();
+        let mut reachable_valencies = self
+            .genesis
+            .valencies
+            .iter()
+            .copied()
+            .collect::<BTreeSet<_>>();
+        let mut transitions = self.transitions.iter().collect::<Vec<_>>();
+        let mut extensions = self.extensions.iter().collect::<Vec<_>>();
+        loop {
+            let count = transitions.len() + extensions.len();
+            transitions.retain(|(_, schema)| {
+                let reachable =
+                    schema.inputs.iter().all(|(ty, occ)| {
+                        occ.min_value() == 0 || reachable_assignments.contains(ty)
+                    }) && schema
+                        .inputs
+                        .keys()
+                        .any(|ty| reachable_assignments.contains(ty));
+                if reachable {
+                    reachable_assignments.extend(schema.assignments.keys());
+                    reachable_valencies.extend(&schema.valencies);
+                }
+                !reachable
+            });
+            extensions.retain(|(_, schema)| {
+                let reachable = schema
+                    .redeems
+                    .iter()
+                    .all(|valency| reachable_valencies.contains(valency));
+                if reachable {
+                    reachable_assignments.extend(schema.assignments.keys());
+                    reachable_valencies.extend(&schema.valencies);
+                }
+                !reachable
+            });
+            if transitions.len() + extensions.len() == count {
+                break;
+            }
+        }
+        lints.extend(
+            transitions
+                .into_iter()
+                .map(|(ty, _)| SchemaLint::UnreachableTransition(*ty)),
+        );
+        lints.extend(
+            extensions
+                .into_iter()
+                .map(|(ty, _)| SchemaLint::UnreachableExtension(*ty)),
+        );
+
+        lints
+    }
+
+    fn diff(&self, other: &Schema) -> SchemaDiff {
+        fn map<K: Ord + Hash + Copy, V: Clone>(
+            map: &TinyOrdMap<K, V>,
+            f: impl Fn(V) -> V,
+        ) -> BTreeMap<K, V> {
+            map.iter().map(|(k, v)| (*k, f(v.clone()))).collect()
+        }
+
+        let mut validators = BTreeSet::new();
+        if self.genesis.validator != other.genesis.validator {
+            validators.insert(OpFullType::Genesis);
+        }
+        for (ty, schema) in &self.transitions {
+            match other.transitions.get(ty) {
+                Some(other) if other.validator != schema.validator => {
+                    validators.insert(OpFullType::StateTransition(*ty));
+                }
+                _ => {}
+            }
+        }
+        for (ty, schema) in &self.extensions {
+            match other.extensions.get(ty) {
+                Some(other) if other.validator != schema.validator => {
+                    validators.insert(OpFullType::StateExtension(*ty));
+                }
+                _ => {}
+            }
+        }
+
+        let mut genesis = self.genesis.clone();
+        genesis.validator = other.genesis.validator;
+        let valencies = |schema: &Schema| {
+            schema
+                .valency_types
+                .iter()
+                .map(|ty| (*ty, ()))
+                .collect::<BTreeMap<_, _>>()
+        };
+
+        SchemaDiff {
+            info_changed: self.name != other.name ||
+                self.developer != other.developer ||
+                self.timestamp != other.timestamp,
+            format_changed: self.ffv != other.ffv ||
+                self.flags != other.flags ||
+                self.reserved != other.reserved,
+            meta_types: TypeDiff::with(
+                &map(&self.meta_types, |v| v),
+                &map(&other.meta_types, |v| v),
+            ),
+            global_types: TypeDiff::with(
+                &map(&self.global_types, |v| v),
+                &map(&other.global_types, |v| v),
+            ),
+            owned_types: TypeDiff::with(
+                &map(&self.owned_types, |v| v),
+                &map(&other.owned_types, |v| v),
+            ),
+            valency_types: TypeDiff::with(&valencies(self), &valencies(other)),
+            genesis_changed: genesis != other.genesis,
+            transitions: TypeDiff::with(
+                &map(&self.transitions, |v| TransitionSchema {
+                    validator: None,
+                    ..v
+                }),
+                &map(&other.transitions, |v| TransitionSchema {
+                    validator: None,
+                    ..v
+                }),
+            ),
+            extensions: TypeDiff::with(
+                &map(&self.extensions, |v| ExtensionSchema {
+                    validator: None,
+                    ..v
+                }),
+                &map(&other.extensions, |v| ExtensionSchema {
+                    validator: None,
+                    ..v
+                }),
+            ),
+            validators,
+        }
+    }
+}
+
+fn lint_owned(
+    schema: &Schema,
+    op: OpFullType,
+    owned: &AssignmentsSchema,
+    lints: &mut Vec<SchemaLint>,
+) {
+    for (ty, occ) in owned {
+        if !schema.owned_types.contains_key(ty) {
+            lints.push(SchemaLint::UndeclaredOwnedType(op, *ty));
+        }
+        if occ.max_value() == 0 || occ.min_value() > occ.max_value() {
+            lints.push(SchemaLint::ImpossibleOwnedOccurrences(op, *ty));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use aluvm::library::{LibId, LibSite};
+    use amplify::confinement::{TinyOrdMap, TinyOrdSet};
+    use rgb::{Occurrences, OwnedStateSchema};
+    use strict_encoding::StrictDumb;
+    use strict_types::SemId;
+
+    use super::*;
+
+    #[test]
+    fn diff() {
+        let mut old = Schema::strict_dumb();
+        old.owned_types = tiny_bmap! { AssignmentType::with(1) => OwnedStateSchema::Declarative };
+        assert!(old.diff(&old).is_empty());
+
+        let mut renamed = old.clone();
+        renamed.timestamp += 1;
+        assert_eq!(old.diff(&renamed).compatibility(), SchemaCompat::WireCompatible);
+
+        let mut rescripted = old.clone();
+        rescripted.genesis.validator = Some(LibSite::with(0, LibId::strict_dumb()));
+        let diff = old.diff(&rescripted);
+        assert_eq!(diff.validators, bset![OpFullType::Genesis]);
+        assert_eq!(diff.compatibility(), SchemaCompat::Breaking);
+        rescripted
+            .owned_types
+            .insert(AssignmentType::with(2), OwnedStateSchema::Declarative)
+            .unwrap();
+        assert_eq!(old.diff(&rescripted).compatibility(), SchemaCompat::Breaking);
+
+        let mut extended = old.clone();
+        extended
+            .owned_types
+            .insert(AssignmentType::with(2), OwnedStateSchema::Declarative)
+            .unwrap();
+        let diff = old.diff(&extended);
+        assert_eq!(diff.owned_types.added, bset![AssignmentType::with(2)]);
+        assert_eq!(diff.compatibility(), SchemaCompat::StateCompatible);
+
+        let diff = extended.diff(&old);
+        assert_eq!(diff.owned_types.removed, bset![AssignmentType::with(2)]);
+        assert_eq!(diff.compatibility(), SchemaCompat::Breaking);
+
+        let mut changed = old.clone();
+        changed.owned_types = tiny_bmap! {
+            AssignmentType::with(1) => OwnedStateSchema::Structured(SemId::strict_dumb())
+        };
+        let diff = old.diff(&changed);
+        assert_eq!(diff.owned_types.changed, bset![AssignmentType::with(1)]);
+        assert_eq!(diff.compatibility(), SchemaCompat::Breaking);
+    }
+
+    #[test]
+    fn lint() {
+        let owned = AssignmentType::with(10);
+        let unused_owned = AssignmentType::with(11);
+        let undeclared_owned = AssignmentType::with(12);
+        let global = GlobalStateType::with(5);
+
+        let mut schema = Schema::strict_dumb();
+        schema.meta_types = tiny_bmap! { MetaType::with(1) => SemId::strict_dumb() };
+        schema.owned_types = tiny_bmap! {
+            owned => OwnedStateSchema::Declarative,
+            unused_owned => OwnedStateSchema::Declarative,
+        };
+        schema.genesis.assignments = tiny_bmap! { owned => Occurrences::Once };
+        schema.genesis.globals = tiny_bmap! { global => Occurrences::Once };
+        schema.transitions = tiny_bmap! {
+            TransitionType::with(1) => TransitionSchema {
+                metadata: TinyOrdSet::new(),
+                globals: TinyOrdMap::new(),
+                inputs: tiny_bmap! { owned => Occurrences::OnceOrMore },
+                assignments: tiny_bmap! { owned => Occurrences::NoneOrMore },
+                valencies: TinyOrdSet::new(),
+                validator: None,
+            },
+            TransitionType::with(2) => TransitionSchema {
+                metadata: TinyOrdSet::new(),
+                globals: TinyOrdMap::new(),
+                inputs: tiny_bmap! { undeclared_owned => Occurrences::Once },
+                assignments: tiny_bmap! { owned => Occurrences::Exactly(0) },
+                valencies: TinyOrdSet::new(),
+                validator: None,
+            },
+        };
+
+        let transition = OpFullType::StateTransition(TransitionType::with(2));
+        let lints = schema.lint().into_iter().collect::<BTreeSet<_>>();
+        assert_eq!(lints, bset![
+            SchemaLint::UnusedMetaType(MetaType::with(1)),
+            SchemaLint::UnassignedOwnedType(unused_owned),
+            SchemaLint::UndeclaredGlobalType(OpFullType::Genesis, global),
+            SchemaLint::UndeclaredOwnedType(transition, undeclared_owned),
+            SchemaLint::ImpossibleOwnedOccurrences(transition, owned),
+            SchemaLint::UnreachableTransition(TransitionType::with(2)),
+        ]);
+    }
+}
@@ -0,0 +1,99 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Entry points for fuzzing decoding and validation of untrusted consignments.
+//!
+//! The functions accept arbitrary bytes and must not panic on any input other
+//! than the documented fuzz oracle assertions, which fail only on a decoding
+//! bug; any other panic found by a fuzzer is a bug as well. Validation runs
+//! offline, resolving only the witness transactions bundled with the decoded
+//! consignment, so the fuzzer controls all the data seen by the validator.
+
+use amplify::confinement::U32;
+use strict_encoding::StrictSerialize;
+
+use crate::containers::{Consignment, DecodeLimits, Transfer};
+use crate::resolvers::BundledTxResolver;
+
+/// Decoding limits used by the fuzzing entry points, small enough for a
+/// single fuzzing iteration to complete quickly.
+pub const FUZZ_DECODE_LIMITS: DecodeLimits = DecodeLimits {
+    max_bundles: 256,
+    max_extensions: 256,
+    max_terminals: 256,
+    max_transitions: 256,
+    max_metadata: 0x10000,
+    max_bytes: 0x100000,
+};
+
+/// Decodes transfer consignment from the `data`, returning `None` if the data
+/// are not a valid consignment encoding.
+///
+/// # Panics
+///
+/// The function acts as a fuzz oracle: it panics if a successfully decoded
+/// consignment doesn't encode back into exactly the same bytes, which
+/// indicates a bug in the decoder.
+pub fn fuzz_decode_consignment(data: &[u8]) -> Option<Transfer> {
+    let consignment = Consignment::strict_decode_limited(data, FUZZ_DECODE_LIMITS).ok()?;
+    let encoded = consignment
+        .to_strict_serialized::<U32>()
+        .expect("decoded consignment must be encodable");
+    if encoded.len() != data.len() {
+        return None;
+    }
+    assert_eq!(&encoded[..], data, "decoded consignment must round-trip");
+    Some(consignment)
+}
+
+/// Decodes transfer consignment from the `data` and validates it against the
+/// witness transactions bundled with it, returning whether the consignment is
+/// valid.
+pub fn fuzz_validate(data: &[u8], testnet: bool) -> bool {
+    let Some(consignment) = fuzz_decode_consignment(data) else {
+        return false;
+    };
+    let mut resolver = BundledTxResolver::with_consignment(&consignment);
+    consignment.validate(&mut resolver, testnet).is_ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::containers::armored_transfer;
+
+    #[test]
+    fn fuzz_entry_points() {
+        assert!(fuzz_decode_consignment(&[]).is_none());
+        assert!(!fuzz_validate(&[0xFF; 64], true));
+
+        let transfer = armored_transfer();
+        let data = transfer.to_strict_serialized::<U32>().unwrap();
+        assert_eq!(fuzz_decode_consignment(&data), Some(transfer));
+        for len in 0..data.len() {
+            assert!(fuzz_decode_consignment(&data[..len]).is_none());
+        }
+        let mut trailing = data.to_vec();
+        trailing.push(0);
+        assert!(fuzz_decode_consignment(&trailing).is_none());
+        fuzz_validate(&data, true);
+    }
+}
@@ -28,14 +28,14 @@ use baid64::{Baid64ParseError, DisplayBaid64, FromBaid64Str};
 use chrono::{DateTime, TimeZone, Utc};
 use commit_verify::{CommitId, CommitmentId, DigestExt, Sha256};
 use rgb::{
-    impl_serde_baid64, AssignmentType, ExtensionType, GlobalStateType, Identity, MetaType, Schema,
-    SchemaId, TransitionType, ValencyType,
+    impl_serde_baid64, AssignmentType, ExtensionType, GlobalStateType, Identity, MetaType,
+    OwnedStateSchema, Schema, SchemaId, TransitionType, ValencyType,
 };
 use strict_encoding::{FieldName, StrictDumb, VariantName};
 use strict_types::encoding::{StrictDecode, StrictEncode, StrictType};
 
 use crate::interface::iface::IfaceId;
-use crate::interface::{Iface, VerNo};
+use crate::interface::{Iface, OwnedIface, VerNo};
 use crate::{ReservedBytes, LIB_NAME_RGB_STD};
 
 pub trait SchemaTypeIndex:
@@ -344,6 +344,9 @@ pub enum ImplInconsistency {
     /// implementation global state field '{0}' maps to an unknown schema global
     /// state type {1}.
     SchemaGlobalAbsent(FieldName, GlobalStateType),
+    /// implementation global state field '{0}' maps to the schema global state
+    /// type {1}, which semantic type doesn't match the interface.
+    GlobalTypeMismatch(FieldName, GlobalStateType),
 
     /// interface owned state field '{0}' is not resolved by the
     /// implementation.
@@ -351,6 +354,9 @@ pub enum ImplInconsistency {
     /// implementation owned state field '{0}' maps to an unknown schema owned
     /// state type {1}.
     SchemaAssignmentAbsent(FieldName, AssignmentType),
+    /// implementation owned state field '{0}' maps to the schema owned state
+    /// type {1}, which kind of state doesn't match the interface.
+    AssignmentTypeMismatch(FieldName, AssignmentType),
 
     /// interface valency field '{0}' is not resolved by the implementation.
     IfaceValencyAbsent(FieldName),
@@ -404,8 +410,18 @@ impl IfaceImpl {
             }
         }
         for field in &self.global_state {
-            if !schema.global_types.contains_key(&field.id) {
+            let Some(global_schema) = schema.global_types.get(&field.id) else {
                 errors.push(ImplInconsistency::SchemaGlobalAbsent(field.name.clone(), field.id));
+                continue;
+            };
+            let Some(global_iface) = iface.global_state.get(&field.name) else {
+                continue;
+            };
+            if global_iface
+                .sem_id
+                .is_some_and(|sem_id| sem_id != global_schema.sem_id)
+            {
+                errors.push(ImplInconsistency::GlobalTypeMismatch(field.name.clone(), field.id));
             }
         }
 
@@ -415,9 +431,28 @@ impl IfaceImpl {
             }
         }
         for field in &self.assignments {
-            if !schema.owned_types.contains_key(&field.id) {
+            let Some(owned_schema) = schema.owned_types.get(&field.id) else {
                 errors
                     .push(ImplInconsistency::SchemaAssignmentAbsent(field.name.clone(), field.id));
+                continue;
+            };
+            let Some(assign_iface) = iface.assignments.get(&field.name) else {
+                continue;
+            };
+            let compatible = match (assign_iface.owned_state, owned_schema) {
+                (OwnedIface::Any, _) |
+                (OwnedIface::Rights, OwnedStateSchema::Declarative) |
+                (OwnedIface::Amount, OwnedStateSchema::Fungible(_)) |
+                (OwnedIface::AnyData, OwnedStateSchema::Structured(_)) |
+                (OwnedIface::AnyAttach, OwnedStateSchema::Attachment(_)) => true,
+                (OwnedIface::Data(sem_id), OwnedStateSchema::Structured(schema_sem_id)) => {
+                    sem_id == *schema_sem_id
+                }
+                _ => false,
+            };
+            if !compatible {
+                errors
+                    .push(ImplInconsistency::AssignmentTypeMismatch(field.name.clone(), field.id));
             }
         }
 
@@ -470,3 +505,85 @@ impl IfaceImpl {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use rgb::{FungibleType, GlobalStateSchema, MediaType};
+    use strict_types::SemId;
+
+    use super::*;
+    use crate::interface::{AssignIface, GlobalIface, Req};
+
+    fn sem_id(byte: u8) -> SemId { SemId::from([byte; 32]) }
+
+    #[test]
+    fn global_type_mismatch() {
+        let ty = GlobalStateType::with(1);
+        let mut schema = Schema::strict_dumb();
+        schema.global_types = tiny_bmap! { ty => GlobalStateSchema::once(sem_id(1)) };
+        let mut iimpl = IfaceImpl::strict_dumb();
+        iimpl.global_state = tiny_bset![NamedField::with(ty, fname!("name"))];
+
+        let mut iface = Iface::strict_dumb();
+        for (global, valid) in [
+            (GlobalIface::required(sem_id(1)), true),
+            (GlobalIface::any(Req::Required), true),
+            (GlobalIface::required(sem_id(2)), false),
+        ] {
+            iface.global_state = tiny_bmap! { fname!("name") => global };
+            let res = iimpl.check(&iface, &schema);
+            if valid {
+                assert_eq!(res, Ok(()));
+            } else {
+                assert_eq!(
+                    res,
+                    Err(vec![ImplInconsistency::GlobalTypeMismatch(fname!("name"), ty)])
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn assignment_type_mismatch() {
+        let ty = AssignmentType::with(1);
+        let mut iimpl = IfaceImpl::strict_dumb();
+        iimpl.assignments = tiny_bset![NamedField::with(ty, fname!("owned"))];
+
+        let owned_schemata = [
+            OwnedStateSchema::Declarative,
+            OwnedStateSchema::Fungible(FungibleType::Unsigned64Bit),
+            OwnedStateSchema::Structured(sem_id(1)),
+            OwnedStateSchema::Attachment(MediaType::Any),
+        ];
+        let owned_ifaces = [
+            (OwnedIface::Any, [true, true, true, true]),
+            (OwnedIface::Rights, [true, false, false, false]),
+            (OwnedIface::Amount, [false, true, false, false]),
+            (OwnedIface::AnyData, [false, false, true, false]),
+            (OwnedIface::AnyAttach, [false, false, false, true]),
+            (OwnedIface::Data(sem_id(1)), [false, false, true, false]),
+            (OwnedIface::Data(sem_id(2)), [false, false, false, false]),
+        ];
+
+        let mut schema = Schema::strict_dumb();
+        let mut iface = Iface::strict_dumb();
+        for (owned_iface, compatible) in owned_ifaces {
+            iface.assignments = tiny_bmap! {
+                fname!("owned") => AssignIface::public(owned_iface, Req::Required),
+            };
+            for (owned_schema, valid) in owned_schemata.iter().zip(compatible) {
+                schema.owned_types = tiny_bmap! { ty => *owned_schema };
+                let res = iimpl.check(&iface, &schema);
+                if valid {
+                    assert_eq!(res, Ok(()), "{owned_iface:?} vs {owned_schema:?}");
+                } else {
+                    assert_eq!(
+                        res,
+                        Err(vec![ImplInconsistency::AssignmentTypeMismatch(fname!("owned"), ty)]),
+                        "{owned_iface:?} vs {owned_schema:?}"
+                    );
+                }
+            }
+        }
+    }
+}
@@ -90,9 +90,16 @@ pub mod persistence;
 pub mod resolvers;
 mod contract;
 pub mod info;
+#[cfg(feature = "test-vectors")]
+pub mod testvec;
+#[cfg(feature = "fuzzing")]
+pub mod fuzz;
 
 pub use bp::{Outpoint, Txid};
-pub use contract::{BundleExt, MergeReveal, MergeRevealError, RevealError, TypedAssignsExt};
+pub use contract::{
+    AggregateError, BundleEntry, BundleExt, MergeReveal, MergeRevealError, RevealError,
+    SchemaCompat, SchemaDiff, SchemaExt, SchemaLint, TypeDiff, TypedAssignsExt,
+};
 pub use invoice::{Allocation, Amount, CoinAmount, OwnedFraction, Precision, TokenIndex};
 pub use rgb::prelude::*;
 pub use rgb::rgbasm;
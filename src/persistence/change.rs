@@ -0,0 +1,125 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Strategies for allocating change of the fungible state spent by a transfer.
+
+use invoice::Amount;
+
+/// Strategy defining how the change of a fungible state is split into
+/// individual allocations, each of which is assigned to its own seal.
+///
+/// More allocations improve privacy by hiding the actual amount of the
+/// transfer, at the cost of the larger state transitions and consignments.
+pub trait ChangeStrategy {
+    /// Splits `change` into the amounts of the change allocations, given the
+    /// amounts of the spent `inputs`.
+    ///
+    /// The returned amounts must be non-zero and must sum up to the `change`.
+    fn split_change(&self, change: Amount, inputs: &[Amount]) -> Vec<Amount>;
+}
+
+/// Allocates all change to a single seal.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct SingleChange;
+
+impl ChangeStrategy for SingleChange {
+    fn split_change(&self, change: Amount, _inputs: &[Amount]) -> Vec<Amount> {
+        if change == Amount::ZERO {
+            return vec![];
+        }
+        vec![change]
+    }
+}
+
+/// Splits change into equal allocations, one per each spent input.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct ChangePerInput;
+
+impl ChangeStrategy for ChangePerInput {
+    fn split_change(&self, change: Amount, inputs: &[Amount]) -> Vec<Amount> {
+        let change = change.value();
+        let count = (inputs.len() as u64).clamp(1, change.max(1));
+        if change == 0 {
+            return vec![];
+        }
+        let part = change / count;
+        let mut parts = vec![Amount::from(part); count as usize];
+        parts[0] += Amount::from(change % count);
+        parts
+    }
+}
+
+/// Splits change into round amounts, one per each non-zero decimal digit of
+/// the change value, such that a change of `1203` produces allocations of
+/// `1000`, `200` and `3`.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct RoundChange;
+
+impl ChangeStrategy for RoundChange {
+    fn split_change(&self, change: Amount, _inputs: &[Amount]) -> Vec<Amount> {
+        let mut change = change.value();
+        let mut denomination = 1u64;
+        let mut parts = vec![];
+        while change > 0 {
+            let digit = change % 10;
+            if digit > 0 {
+                parts.push(Amount::from(digit * denomination));
+            }
+            change /= 10;
+            denomination = denomination.saturating_mul(10);
+        }
+        parts.reverse();
+        parts
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn amounts(values: impl IntoIterator<Item = u64>) -> Vec<Amount> {
+        values.into_iter().map(Amount::from).collect()
+    }
+
+    #[test]
+    fn split_change() {
+        let inputs = amounts([500, 700, 100]);
+
+        assert_eq!(SingleChange.split_change(Amount::ZERO, &inputs), vec![]);
+        assert_eq!(SingleChange.split_change(Amount::from(1203u64), &inputs), amounts([1203]));
+
+        assert_eq!(ChangePerInput.split_change(Amount::ZERO, &inputs), vec![]);
+        assert_eq!(
+            ChangePerInput.split_change(Amount::from(1204u64), &inputs),
+            amounts([402, 401, 401])
+        );
+        assert_eq!(ChangePerInput.split_change(Amount::from(2u64), &inputs), amounts([1, 1]));
+        assert_eq!(ChangePerInput.split_change(Amount::from(5u64), &[]), amounts([5]));
+
+        assert_eq!(RoundChange.split_change(Amount::ZERO, &inputs), vec![]);
+        assert_eq!(
+            RoundChange.split_change(Amount::from(1203u64), &inputs),
+            amounts([1000, 200, 3])
+        );
+        let max = RoundChange.split_change(Amount::from(u64::MAX), &inputs);
+        assert_eq!(max.into_iter().sum::<Amount>(), Amount::from(u64::MAX));
+    }
+}
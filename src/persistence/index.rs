@@ -285,6 +285,12 @@ impl<P: IndexProvider> Index<P> {
         Ok(())
     }
 
+    /// Removes information about the bundle, its operations and their
+    /// assignments from the index, keeping the bundle itself in the stash.
+    pub(crate) fn unindex_bundle(&mut self, bundle_id: BundleId) -> Result<bool, IndexError<P>> {
+        Ok(self.provider.unregister_bundle(bundle_id)?)
+    }
+
     pub(super) fn contracts_assigning(
         &self,
         outputs: BTreeSet<XOutputSeal>,
@@ -382,6 +388,21 @@ pub trait IndexWriteProvider {
         bundle_id: BundleId,
     ) -> Result<bool, IndexWriteError<Self::Error>>;
 
+    /// Removes the bundle, its operations and their assignments from the
+    /// index. Returns `false` if the bundle was not indexed.
+    ///
+    /// Providers which can't remove indexed data may keep the default
+    /// implementation, which always returns `false`; in this case
+    /// [`crate::persistence::Stock::rollback`] fails without modifying the
+    /// contract state.
+    fn unregister_bundle(
+        &mut self,
+        bundle_id: BundleId,
+    ) -> Result<bool, IndexWriteError<Self::Error>> {
+        let _ = bundle_id;
+        Ok(false)
+    }
+
     fn index_genesis_assignments<State: ExposedState>(
         &mut self,
         contract_id: ContractId,
@@ -661,6 +661,56 @@ impl IndexWriteProvider for MemIndex {
         Ok(!present)
     }
 
+    fn unregister_bundle(
+        &mut self,
+        bundle_id: BundleId,
+    ) -> Result<bool, IndexWriteError<Self::Error>> {
+        let Some(contract_id) = self.bundle_contract_index.remove(&bundle_id)? else {
+            return Ok(false);
+        };
+        self.bundle_witness_index.remove(&bundle_id)?;
+
+        let opids = self
+            .op_bundle_index
+            .iter()
+            .filter(|(_, id)| **id == bundle_id)
+            .map(|(opid, _)| *opid)
+            .collect::<BTreeSet<_>>();
+        for opid in &opids {
+            self.op_bundle_index.remove(opid)?;
+        }
+
+        if let Some(index) = self.contract_index.get_mut(&contract_id) {
+            let stale = index
+                .outpoint_opouts
+                .iter()
+                .flat_map(|(output, opouts)| opouts.iter().map(move |opout| (*output, *opout)))
+                .filter(|(_, opout)| opids.contains(&opout.op))
+                .collect::<Vec<_>>();
+            for (output, opout) in stale {
+                let opouts = index
+                    .outpoint_opouts
+                    .get_mut(&output)
+                    .expect("output is taken from the index");
+                opouts.remove(&opout)?;
+                if opouts.is_empty() {
+                    index.outpoint_opouts.remove(&output)?;
+                }
+            }
+        }
+
+        let terminals = self
+            .terminal_index
+            .iter()
+            .filter(|(_, opout)| opids.contains(&opout.op))
+            .map(|(seal, _)| *seal)
+            .collect::<Vec<_>>();
+        for seal in terminals {
+            self.terminal_index.remove(&seal)?;
+        }
+        Ok(true)
+    }
+
     fn index_genesis_assignments<State: ExposedState>(
         &mut self,
         contract_id: ContractId,
@@ -34,11 +34,13 @@ mod stock;
 mod stash;
 mod state;
 mod index;
+mod change;
 
 mod memory;
 #[cfg(feature = "fs")]
 pub mod fs;
 
+pub use change::{ChangePerInput, ChangeStrategy, RoundChange, SingleChange};
 pub use index::{
     Index, IndexError, IndexInconsistency, IndexProvider, IndexReadError, IndexReadProvider,
     IndexWriteError, IndexWriteProvider,
@@ -52,6 +54,6 @@ pub use state::{
     PersistedState, StateProvider, StateReadProvider, StateUpdateError, StateWriteProvider,
 };
 pub use stock::{
-    ComposeError, ConsignError, ContractIfaceError, FasciaError, InputError as StockInputError,
-    Stock, StockError, StockErrorAll, StockErrorMem,
+    ComposeError, ConsignError, ContractIfaceError, DisclosureError, FasciaError,
+    InputError as StockInputError, Stock, StockError, StockErrorAll, StockErrorMem,
 };
@@ -34,23 +34,24 @@ use chrono::Utc;
 use commit_verify::Conceal;
 use invoice::{Amount, Beneficiary, InvoiceState, NonFungible, RgbInvoice};
 use rgb::{
-    validation, AssignmentType, BlindingFactor, BundleId, ContractHistory, ContractId,
-    ContractState, DbcProof, EAnchor, GraphSeal, Identity, OpId, Operation, Opout, SchemaId,
-    SecretSeal, Transition, WitnessAnchor, XChain, XOutpoint, XOutputSeal, XWitnessId,
+    validation, AssignmentType, AssignmentWitness, BlindingFactor, BundleId, ContractHistory,
+    ContractId, ContractState, DbcProof, EAnchor, GraphSeal, Identity, Layer1, OpId, Operation,
+    Opout, SchemaId, SecretSeal, Transition, WitnessAnchor, XChain, XOutpoint, XOutputSeal,
+    XWitnessId,
 };
 use strict_encoding::FieldName;
 
 use super::{
-    Index, IndexError, IndexInconsistency, IndexProvider, IndexReadProvider, IndexWriteProvider,
-    MemIndex, MemStash, MemState, PersistedState, SchemaIfaces, Stash, StashDataError, StashError,
-    StashInconsistency, StashProvider, StashReadProvider, StashWriteProvider, StateProvider,
-    StateReadProvider, StateUpdateError, StateWriteProvider,
+    ChangeStrategy, Index, IndexError, IndexInconsistency, IndexProvider, IndexReadProvider,
+    IndexWriteProvider, MemIndex, MemStash, MemState, PersistedState, SchemaIfaces, SingleChange,
+    Stash, StashDataError, StashError, StashInconsistency, StashProvider, StashReadProvider,
+    StashWriteProvider, StateProvider, StateReadProvider, StateUpdateError, StateWriteProvider,
 };
 use crate::containers::{
     AnchorSet, AnchoredBundles, Batch, BuilderSeal, BundledWitness, Consignment, ContainerVer,
-    ContentRef, Contract, Fascia, Kit, SealWitness, SupplItem, SupplSub, Terminal, TerminalSeal,
-    Transfer, TransitionInfo, TransitionInfoError, ValidConsignment, ValidContract, ValidKit,
-    ValidTransfer, VelocityHint, SUPPL_ANNOT_VELOCITY,
+    ContentRef, Contract, Disclosure, Fascia, Kit, SealWitness, SupplItem, SupplSub, Terminal,
+    TerminalSeal, Transfer, TransitionInfo, TransitionInfoError, ValidConsignment, ValidContract,
+    ValidKit, ValidTransfer, VelocityHint, SUPPL_ANNOT_VELOCITY,
 };
 use crate::info::{ContractInfo, IfaceInfo, SchemaInfo};
 use crate::interface::resolver::DumbResolver;
@@ -58,7 +59,7 @@ use crate::interface::{
     BuilderError, ContractBuilder, ContractIface, Iface, IfaceClass, IfaceId, IfaceRef,
     TransitionBuilder,
 };
-use crate::resolvers::ResolveHeight;
+use crate::resolvers::{ResolveHeight, WitnessStatus};
 use crate::{MergeRevealError, RevealError};
 
 pub type ContractAssignments = HashMap<XOutputSeal, HashMap<Opout, PersistedState>>;
@@ -106,6 +107,11 @@ pub enum StockError<
 
     #[from]
     StashData(StashDataError),
+
+    #[display(doc_comments)]
+    /// index provider can't unregister bundle {0}, so the contract state can't
+    /// be rolled back.
+    RollbackUnsupported(BundleId),
 }
 
 impl<S: StashProvider, H: StateProvider, P: IndexProvider, E: Error> From<StashError<S>>
@@ -218,6 +224,13 @@ pub enum ComposeError {
     /// transition input limit.
     TooManyInputs,
 
+    /// sum of the spent fungible state of type {0} exceeds the maximum value.
+    InputsOverflow(AssignmentType),
+
+    /// change strategy has split change of {0} into allocations which are
+    /// either zero or do not sum up to the change value.
+    InvalidChange(Amount),
+
     #[from]
     #[display(inner)]
     Transition(TransitionInfoError),
@@ -256,6 +269,30 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> From<FasciaError>
     fn from(err: FasciaError) -> Self { Self::InvalidInput(err) }
 }
 
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum DisclosureError {
+    /// disclosure contains bundle {0} under a different id {1}.
+    BundleIdMismatch(BundleId, BundleId),
+
+    /// disclosure reveals bundle {0} which is not known to the stash.
+    UnknownBundle(BundleId),
+
+    /// disclosed bundle {0} contains transition {1} which doesn't belong to
+    /// the bundle or its contract.
+    UnrelatedTransition(BundleId, OpId),
+
+    /// disclosure reveals seal {0} which is not used by any of the disclosed
+    /// or known operations.
+    UnrelatedSeal(XChain<SecretSeal>),
+}
+
+impl<S: StashProvider, H: StateProvider, P: IndexProvider> From<DisclosureError>
+    for StockError<S, H, P, DisclosureError>
+{
+    fn from(err: DisclosureError) -> Self { Self::InvalidInput(err) }
+}
+
 #[derive(Clone, PartialEq, Eq, Debug, Display, Error, From)]
 #[display(doc_comments)]
 pub enum ContractIfaceError {
@@ -302,6 +339,7 @@ macro_rules! stock_err_conv {
                     StockError::StashInconsistency(e) => StockError::StashInconsistency(e),
                     StockError::StateInconsistency(e) => StockError::StateInconsistency(e),
                     StockError::IndexInconsistency(e) => StockError::IndexInconsistency(e),
+                    StockError::RollbackUnsupported(e) => StockError::RollbackUnsupported(e),
                 }
             }
         }
@@ -460,6 +498,46 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> Stock<S, H, P> {
         })
     }
 
+    /// Resolves mining status of all witness transactions which have assigned
+    /// some state of the contract.
+    ///
+    /// The returned map can be filtered by the status and used as a
+    /// [`crate::interface::WitnessFilter`], for instance to exclude state which
+    /// is not settled yet.
+    ///
+    /// Statuses are not persisted in the contract state, since they change
+    /// with each new block; instead the witness height is resolved on each
+    /// call and the number of confirmations is computed against `tip_height`.
+    pub fn witness_statuses<R: ResolveHeight>(
+        &self,
+        contract_id: ContractId,
+        resolver: &mut R,
+        tip_height: u32,
+        min_confirmations: u32,
+    ) -> Result<BTreeMap<AssignmentWitness, WitnessStatus>, StockError<S, H, P>> {
+        let (_, history, _) = self.contract_raw(contract_id)?;
+        let witnesses = history
+            .rights()
+            .iter()
+            .map(|a| a.witness)
+            .chain(history.fungibles().iter().map(|a| a.witness))
+            .chain(history.data().iter().map(|a| a.witness))
+            .chain(history.attach().iter().map(|a| a.witness))
+            .collect::<BTreeSet<_>>();
+        let mut statuses = BTreeMap::new();
+        for witness in witnesses {
+            let AssignmentWitness::Present(witness_id) = witness else {
+                continue;
+            };
+            let anchor = resolver
+                .resolve_height(witness_id)
+                .map_err(StockError::Resolver)?;
+            let status = WitnessStatus::with(anchor.witness_ord, tip_height, min_confirmations);
+            statuses.insert(witness, status);
+        }
+        Ok(statuses)
+    }
+
     pub fn contract_iface_class<C: IfaceClass>(
         &self,
         contract_id: ContractId,
@@ -802,6 +880,34 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> Stock<S, H, P> {
         )
     }
 
+    /// Composes a batch of state transitions like [`Self::compose`], splitting
+    /// fungible change into allocations according to the `change_strategy`.
+    ///
+    /// The `allocator` receives the number of the allocation of the given
+    /// assignment type within the transition, such that each of the
+    /// allocations can get its own output.
+    #[allow(clippy::result_large_err)]
+    pub fn compose_with_change(
+        &self,
+        invoice: &RgbInvoice,
+        prev_outputs: impl IntoIterator<Item = impl Into<XOutputSeal>>,
+        method: CloseMethod,
+        beneficiary_vout: Option<impl Into<Vout>>,
+        allocator: impl Fn(ContractId, AssignmentType, VelocityHint, u16) -> Option<Vout>,
+        change_strategy: impl ChangeStrategy,
+    ) -> Result<Batch, StockError<S, H, P, ComposeError>> {
+        self.compose_deterministic_with_change(
+            invoice,
+            prev_outputs,
+            method,
+            beneficiary_vout,
+            allocator,
+            change_strategy,
+            |_, _, _| BlindingFactor::random(),
+            |_, _, _| rand::random(),
+        )
+    }
+
     /// Composes a batch of state transitions updating state for the provided
     /// set of previous outputs, satisfying requirements of the invoice, paying
     /// the change back and including the necessary blank state transitions.
@@ -815,6 +921,63 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> Stock<S, H, P> {
         allocator: impl Fn(ContractId, AssignmentType, VelocityHint) -> Option<Vout>,
         pedersen_blinder: impl Fn(ContractId, AssignmentType) -> BlindingFactor,
         seal_blinder: impl Fn(ContractId, AssignmentType) -> u64,
+    ) -> Result<Batch, StockError<S, H, P, ComposeError>> {
+        self.compose_internal(
+            invoice,
+            prev_outputs,
+            method,
+            beneficiary_vout,
+            |id, ty, hint, _| allocator(id, ty, hint),
+            SingleChange,
+            |id, main_ty, _, _| pedersen_blinder(id, main_ty),
+            |id, ty, _| seal_blinder(id, ty),
+        )
+    }
+
+    /// Composes a batch of state transitions like
+    /// [`Self::compose_deterministic`], splitting fungible change into
+    /// allocations according to the `change_strategy`.
+    ///
+    /// The `allocator` and the blinders receive the number of the allocation
+    /// of the given assignment type within the transition, such that each of
+    /// the allocations can get its own output and blinding.
+    #[allow(clippy::too_many_arguments, clippy::result_large_err)]
+    pub fn compose_deterministic_with_change(
+        &self,
+        invoice: &RgbInvoice,
+        prev_outputs: impl IntoIterator<Item = impl Into<XOutputSeal>>,
+        method: CloseMethod,
+        beneficiary_vout: Option<impl Into<Vout>>,
+        allocator: impl Fn(ContractId, AssignmentType, VelocityHint, u16) -> Option<Vout>,
+        change_strategy: impl ChangeStrategy,
+        pedersen_blinder: impl Fn(ContractId, AssignmentType, u16) -> BlindingFactor,
+        seal_blinder: impl Fn(ContractId, AssignmentType, u16) -> u64,
+    ) -> Result<Batch, StockError<S, H, P, ComposeError>> {
+        self.compose_internal(
+            invoice,
+            prev_outputs,
+            method,
+            beneficiary_vout,
+            allocator,
+            change_strategy,
+            |id, _, ty, no| pedersen_blinder(id, ty, no),
+            seal_blinder,
+        )
+    }
+
+    /// The `pedersen_blinder` receives the assignment type of the invoice
+    /// together with the type and the number of the blinded allocation.
+    #[allow(clippy::too_many_arguments, clippy::result_large_err)]
+    fn compose_internal(
+        &self,
+        invoice: &RgbInvoice,
+        prev_outputs: impl IntoIterator<Item = impl Into<XOutputSeal>>,
+        method: CloseMethod,
+        beneficiary_vout: Option<impl Into<Vout>>,
+        allocator: impl Fn(ContractId, AssignmentType, VelocityHint, u16) -> Option<Vout>,
+        change_strategy: impl ChangeStrategy,
+        pedersen_blinder: impl Fn(ContractId, AssignmentType, AssignmentType, u16) -> BlindingFactor,
+        seal_blinder: impl Fn(ContractId, AssignmentType, u16) -> u64,
     ) -> Result<Batch, StockError<S, H, P, ComposeError>> {
         let layer1 = invoice.layer1();
         let prev_outputs = prev_outputs
@@ -822,32 +985,23 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> Stock<S, H, P> {
             .map(|o| o.into())
             .collect::<HashSet<XOutputSeal>>();
 
-        #[allow(clippy::type_complexity)]
-        let output_for_assignment =
-            |id: ContractId,
-             assignment_type: AssignmentType|
-             -> Result<BuilderSeal<GraphSeal>, StockError<S, H, P, ComposeError>> {
-                let mut suppl = self.stash.supplements(ContentRef::Genesis(id))?;
-                let velocity = suppl
-                    .next()
-                    .and_then(|suppl| {
-                        suppl
-                            .get(
-                                SupplSub::Assignment,
-                                SupplItem::TypeNo(assignment_type.to_inner()),
-                                SUPPL_ANNOT_VELOCITY,
-                            )
-                            .transpose()
-                            .ok()
-                            .flatten()
-                    })
-                    .unwrap_or_default();
-                let vout = allocator(id, assignment_type, velocity)
-                    .ok_or(ComposeError::NoBlankOrChange(velocity, assignment_type))?;
-                let seal =
-                    GraphSeal::with_blinded_vout(method, vout, seal_blinder(id, assignment_type));
-                Ok(BuilderSeal::Revealed(XChain::with(layer1, seal)))
-            };
+        let output_for_assignment = |id: ContractId, assignment_type: AssignmentType, no: u16| {
+            self.output_for_assignment(
+                id,
+                assignment_type,
+                no,
+                method,
+                layer1,
+                &allocator,
+                &seal_blinder,
+            )
+        };
+        let mut allocations = BTreeMap::<AssignmentType, u16>::new();
+        let mut next_allocation = |assignment_type: AssignmentType| {
+            let no = allocations.entry(assignment_type).or_default();
+            *no += 1;
+            *no - 1
+        };
 
         // 1. Prepare the data
         if let Some(expiry) = invoice.expiry {
@@ -870,6 +1024,7 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> Stock<S, H, P> {
             .ok_or(BuilderError::InvalidStateField(assignment_name.clone()))?;
 
         let layer1 = invoice.beneficiary.chain_network().layer1();
+        let beneficiary_no = next_allocation(assignment_id);
         let beneficiary = match (invoice.beneficiary.into_inner(), beneficiary_vout) {
             (Beneficiary::BlindedSeal(seal), None) => {
                 BuilderSeal::Concealed(XChain::with(layer1, seal))
@@ -878,7 +1033,7 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> Stock<S, H, P> {
                 return Err(ComposeError::BeneficiaryVout.into());
             }
             (Beneficiary::WitnessVout(payload), Some(vout)) => {
-                let blinding = seal_blinder(contract_id, assignment_id);
+                let blinding = seal_blinder(contract_id, assignment_id, beneficiary_no);
                 let seal = GraphSeal::with_blinded_vout(payload.method, vout, blinding);
                 BuilderSeal::Revealed(XChain::with(layer1, seal))
             }
@@ -890,6 +1045,7 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> Stock<S, H, P> {
         // 2. Prepare transition
         let mut main_inputs = Vec::<XOutputSeal>::new();
         let mut sum_inputs = Amount::ZERO;
+        let mut amount_inputs = vec![];
         let mut data_inputs = vec![];
         for (output, list) in
             self.contract_assignments_for(contract_id, prev_outputs.iter().copied())?
@@ -898,11 +1054,20 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> Stock<S, H, P> {
             for (opout, mut state) in list {
                 main_builder = main_builder.add_input(opout, state.clone())?;
                 if opout.ty != assignment_id {
-                    let seal = output_for_assignment(contract_id, opout.ty)?;
-                    state.update_blinding(pedersen_blinder(contract_id, assignment_id));
+                    let no = next_allocation(opout.ty);
+                    let seal = output_for_assignment(contract_id, opout.ty, no)?;
+                    state.update_blinding(pedersen_blinder(
+                        contract_id,
+                        assignment_id,
+                        opout.ty,
+                        no,
+                    ));
                     main_builder = main_builder.add_owned_state_raw(opout.ty, seal, state)?;
                 } else if let PersistedState::Amount(value, _, _) = state {
-                    sum_inputs += value;
+                    amount_inputs.push(value);
+                    sum_inputs = sum_inputs
+                        .checked_add(value)
+                        .ok_or(ComposeError::InputsOverflow(assignment_id))?;
                 } else if let PersistedState::Data(value, _) = state {
                     data_inputs.push(value);
                 }
@@ -913,13 +1078,23 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> Stock<S, H, P> {
             InvoiceState::Amount(amt) => {
                 match sum_inputs.cmp(&amt) {
                     Ordering::Greater => {
-                        let seal = output_for_assignment(contract_id, assignment_id)?;
-                        main_builder = main_builder.add_fungible_state_raw(
-                            assignment_id,
-                            seal,
-                            sum_inputs - amt,
-                            pedersen_blinder(contract_id, assignment_id),
-                        )?;
+                        let change = sum_inputs - amt;
+                        let allocations = change_strategy.split_change(change, &amount_inputs);
+                        if allocations.contains(&Amount::ZERO) ||
+                            allocations.iter().copied().sum::<Amount>() != change
+                        {
+                            return Err(ComposeError::InvalidChange(change).into());
+                        }
+                        for amount in allocations {
+                            let no = next_allocation(assignment_id);
+                            let seal = output_for_assignment(contract_id, assignment_id, no)?;
+                            main_builder = main_builder.add_fungible_state_raw(
+                                assignment_id,
+                                seal,
+                                amount,
+                                pedersen_blinder(contract_id, assignment_id, assignment_id, no),
+                            )?;
+                        }
                     }
                     Ordering::Less => return Err(ComposeError::InsufficientState.into()),
                     Ordering::Equal => {}
@@ -929,7 +1104,7 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> Stock<S, H, P> {
                         assignment_id,
                         beneficiary,
                         amt,
-                        pedersen_blinder(contract_id, assignment_id),
+                        pedersen_blinder(contract_id, assignment_id, assignment_id, beneficiary_no),
                     )?
                     .complete_transition()?
             }
@@ -944,7 +1119,7 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> Stock<S, H, P> {
                             assignment_id,
                             beneficiary,
                             allocation,
-                            seal_blinder(contract_id, assignment_id),
+                            seal_blinder(contract_id, assignment_id, beneficiary_no),
                         )?
                         .complete_transition()?
                 }
@@ -958,12 +1133,76 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> Stock<S, H, P> {
         };
 
         // 3. Prepare other transitions
+        let blanks = self.compose_blanks_deterministic(
+            prev_outputs,
+            Some(contract_id),
+            iface.clone(),
+            method,
+            layer1,
+            &allocator,
+            &seal_blinder,
+        )?;
+
+        let main = TransitionInfo::new(main_transition, main_inputs)
+            .map_err(|_| ComposeError::TooManyInputs)?;
+        Ok(Batch { main, blanks })
+    }
+
+    /// Composes blank state transitions for all contracts, except the
+    /// `excluded` one, which have state assigned to the provided set of
+    /// previous outputs, using random seal blinding.
+    ///
+    /// See [`Self::compose_blanks_deterministic`] for the details.
+    #[allow(clippy::result_large_err)]
+    pub fn compose_blanks(
+        &self,
+        prev_outputs: impl IntoIterator<Item = impl Into<XOutputSeal>>,
+        excluded: Option<ContractId>,
+        iface: impl Into<IfaceRef> + Clone,
+        method: CloseMethod,
+        layer1: Layer1,
+        allocator: impl Fn(ContractId, AssignmentType, VelocityHint) -> Option<Vout>,
+    ) -> Result<Confined<Vec<TransitionInfo>, 0, { U24 - 1 }>, StockError<S, H, P, ComposeError>>
+    {
+        self.compose_blanks_deterministic(
+            prev_outputs,
+            excluded,
+            iface,
+            method,
+            layer1,
+            |id, ty, hint, _| allocator(id, ty, hint),
+            |_, _, _| rand::random(),
+        )
+    }
+
+    /// Composes blank state transitions for all contracts, except the
+    /// `excluded` one, which have state assigned to the provided set of
+    /// previous outputs. Each blank transition moves all the spent state of a
+    /// contract to the outputs returned by the `allocator`, with seals blinded
+    /// by the `seal_blinder`. Both receive the number of the allocation of the
+    /// assignment type within the blank transition.
+    #[allow(clippy::too_many_arguments, clippy::result_large_err)]
+    pub fn compose_blanks_deterministic(
+        &self,
+        prev_outputs: impl IntoIterator<Item = impl Into<XOutputSeal>>,
+        excluded: Option<ContractId>,
+        iface: impl Into<IfaceRef> + Clone,
+        method: CloseMethod,
+        layer1: Layer1,
+        allocator: impl Fn(ContractId, AssignmentType, VelocityHint, u16) -> Option<Vout>,
+        seal_blinder: impl Fn(ContractId, AssignmentType, u16) -> u64,
+    ) -> Result<Confined<Vec<TransitionInfo>, 0, { U24 - 1 }>, StockError<S, H, P, ComposeError>>
+    {
+        let prev_outputs = prev_outputs
+            .into_iter()
+            .map(|o| o.into())
+            .collect::<HashSet<XOutputSeal>>();
+
         // Enumerate state
         let mut spent_state =
             HashMap::<ContractId, HashMap<XOutputSeal, HashMap<Opout, PersistedState>>>::new();
         for id in self.contracts_assigning(prev_outputs.iter().copied())? {
-            // Skip current contract
-            if id == contract_id {
+            if Some(id) == excluded {
                 continue;
             }
             let state = self.contract_assignments_for(id, prev_outputs.iter().copied())?;
@@ -978,10 +1217,21 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> Stock<S, H, P> {
         for (id, list) in spent_state {
             let mut blank_builder = self.blank_builder(id, iface.clone())?;
             let mut outputs = Vec::with_capacity(list.len());
+            let mut allocations = BTreeMap::<AssignmentType, u16>::new();
             for (output, assigns) in list {
                 outputs.push(output);
                 for (opout, state) in assigns {
-                    let seal = output_for_assignment(id, opout.ty)?;
+                    let no = allocations.entry(opout.ty).or_default();
+                    let seal = self.output_for_assignment(
+                        id,
+                        opout.ty,
+                        *no,
+                        method,
+                        layer1,
+                        &allocator,
+                        &seal_blinder,
+                    )?;
+                    *no += 1;
                     blank_builder = blank_builder
                         .add_input(opout, state.clone())?
                         .add_owned_state_raw(opout.ty, seal, state)?;
@@ -994,9 +1244,41 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> Stock<S, H, P> {
             blanks.push(info).map_err(|_| ComposeError::TooManyBlanks)?;
         }
 
-        let main = TransitionInfo::new(main_transition, main_inputs)
-            .map_err(|_| ComposeError::TooManyInputs)?;
-        Ok(Batch { main, blanks })
+        Ok(blanks)
+    }
+
+    #[allow(clippy::result_large_err)]
+    #[allow(clippy::too_many_arguments)]
+    fn output_for_assignment(
+        &self,
+        id: ContractId,
+        assignment_type: AssignmentType,
+        no: u16,
+        method: CloseMethod,
+        layer1: Layer1,
+        allocator: impl Fn(ContractId, AssignmentType, VelocityHint, u16) -> Option<Vout>,
+        seal_blinder: impl Fn(ContractId, AssignmentType, u16) -> u64,
+    ) -> Result<BuilderSeal<GraphSeal>, StockError<S, H, P, ComposeError>> {
+        let mut suppl = self.stash.supplements(ContentRef::Genesis(id))?;
+        let velocity = suppl
+            .next()
+            .and_then(|suppl| {
+                suppl
+                    .get(
+                        SupplSub::Assignment,
+                        SupplItem::TypeNo(assignment_type.to_inner()),
+                        SUPPL_ANNOT_VELOCITY,
+                    )
+                    .transpose()
+                    .ok()
+                    .flatten()
+            })
+            .unwrap_or_default();
+        let vout = allocator(id, assignment_type, velocity, no)
+            .ok_or(ComposeError::NoBlankOrChange(velocity, assignment_type))?;
+        let seal =
+            GraphSeal::with_blinded_vout(method, vout, seal_blinder(id, assignment_type, no));
+        Ok(BuilderSeal::Revealed(XChain::with(layer1, seal)))
     }
 
     pub fn import_kit(&mut self, kit: ValidKit) -> Result<validation::Status, StockError<S, H, P>> {
@@ -1083,6 +1365,187 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> Stock<S, H, P> {
         Ok(())
     }
 
+    /// Accepts disclosure revealing concealed state of the bundles which are
+    /// already known to the stash, updating the contract state with the
+    /// revealed assignments.
+    ///
+    /// Each of the disclosed seals must be concealed either in the disclosed
+    /// bundles or in the operations already known to the stock.
+    pub fn accept_disclosure<R: ResolveHeight>(
+        &mut self,
+        disclosure: Disclosure,
+        resolver: &mut R,
+    ) -> Result<(), StockError<S, H, P, DisclosureError>> {
+        let mut bundles = Vec::with_capacity(disclosure.bundles.len());
+        for (bundle_id, bundle) in disclosure.bundles {
+            if bundle.bundle_id() != bundle_id {
+                return Err(DisclosureError::BundleIdMismatch(bundle_id, bundle.bundle_id()).into());
+            }
+            let (witness_id, contract_id) = self
+                .index
+                .bundle_info(bundle_id)
+                .map_err(|_| DisclosureError::UnknownBundle(bundle_id))?;
+            for (opid, transition) in &bundle.known_transitions {
+                if transition.id() != *opid ||
+                    transition.contract_id != contract_id ||
+                    !bundle.input_map.values().any(|id| id == opid)
+                {
+                    return Err(DisclosureError::UnrelatedTransition(bundle_id, *opid).into());
+                }
+            }
+            bundles.push((contract_id, witness_id, bundle));
+        }
+
+        let disclosed_seals = bundles
+            .iter()
+            .flat_map(|(_, _, bundle)| bundle.known_transitions.values())
+            .flat_map(|transition| transition.assignments.iter())
+            .flat_map(|(_, assigns)| assigns.to_confidential_seals())
+            .collect::<BTreeSet<_>>();
+        for seal in &disclosure.seals {
+            let secret = seal.conceal();
+            if !disclosed_seals.contains(&secret) &&
+                self.index.opouts_by_terminals([secret])?.is_empty()
+            {
+                return Err(DisclosureError::UnrelatedSeal(secret).into());
+            }
+        }
+
+        for seal in disclosure.seals {
+            self.stash.store_secret_seal(seal)?;
+        }
+        for (contract_id, witness_id, bundle) in bundles {
+            let witness_anchor = resolver
+                .resolve_height(witness_id)
+                .map_err(StockError::Resolver)?;
+            self.stash.consume_bundle(bundle.clone())?;
+            let bundle = self.stash.bundle(bundle.bundle_id())?.clone();
+            self.index.index_bundle(contract_id, &bundle, witness_id)?;
+            self.state.update_state::<R>(contract_id, |history| {
+                for transition in bundle.known_transitions.values() {
+                    history.add_transition(transition, witness_anchor);
+                }
+                Ok(())
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Rolls back contract state derived from the witness transactions which
+    /// are no longer valid, for instance disappeared from the blockchain after
+    /// a reorg or replaced in the mempool.
+    ///
+    /// State of all contracts affected by the `invalid` witnesses is
+    /// re-computed from the stash, skipping the bundles anchored to these
+    /// witnesses. The caller must include into the `invalid` set all
+    /// witnesses which were spending outputs of the invalidated ones.
+    ///
+    /// The skipped bundles are kept in the stash and their ids are returned,
+    /// so that the bundles may be re-anchored into a new witness transaction.
+    /// They are removed from the index, such that subsequent rollbacks do not
+    /// restore their state; re-anchoring them with [`Self::consume_fascia`]
+    /// indexes them again under the new witness.
+    ///
+    /// # Errors
+    ///
+    /// With [`StockError::RollbackUnsupported`] if the index provider can't
+    /// unregister bundles (see [`IndexWriteProvider::unregister_bundle`]); the
+    /// contract state is not modified in this case.
+    pub fn rollback<R: ResolveHeight>(
+        &mut self,
+        invalid: &BTreeSet<XWitnessId>,
+        resolver: &mut R,
+    ) -> Result<BTreeSet<BundleId>, StockError<S, H, P>> {
+        let mut bundles = BTreeMap::<ContractId, Vec<(BundleId, XWitnessId)>>::new();
+        for bundle_id in self
+            .stash
+            .as_provider()
+            .bundle_ids()
+            .map_err(StashError::ReadProvider)?
+        {
+            let (witness_id, contract_id) = match self.index.bundle_info(bundle_id) {
+                Ok(info) => info,
+                // Bundles rolled back before are no longer indexed
+                Err(IndexError::Inconsistency(IndexInconsistency::BundleWitnessUnknown(_))) => {
+                    continue;
+                }
+                Err(err) => return Err(err.into()),
+            };
+            bundles
+                .entry(contract_id)
+                .or_default()
+                .push((bundle_id, witness_id));
+        }
+
+        // Histories are computed before any update, so a resolver failure
+        // leaves both the index and the state untouched
+        let mut rolled_back = BTreeSet::new();
+        let mut histories = Vec::new();
+        for (contract_id, list) in bundles {
+            if list
+                .iter()
+                .all(|(_, witness_id)| !invalid.contains(witness_id))
+            {
+                continue;
+            }
+
+            let schema_id = self.contract_raw(contract_id)?.1.schema_id();
+            let genesis = self.stash.genesis(contract_id)?;
+            let mut history = ContractHistory::with(schema_id, contract_id, genesis);
+            let mut extension_anchors = BTreeMap::<OpId, WitnessAnchor>::new();
+            for (bundle_id, witness_id) in list {
+                if invalid.contains(&witness_id) {
+                    rolled_back.insert(bundle_id);
+                    continue;
+                }
+                let witness_anchor = resolver
+                    .resolve_height(witness_id)
+                    .map_err(StockError::Resolver)?;
+                for transition in self.stash.bundle(bundle_id)?.known_transitions.values() {
+                    history.add_transition(transition, witness_anchor);
+                    for input in &transition.inputs {
+                        extension_anchors
+                            .entry(input.prev_out.op)
+                            .and_modify(|anchor| *anchor = witness_anchor.min(*anchor))
+                            .or_insert(witness_anchor);
+                    }
+                }
+            }
+            for opid in self
+                .stash
+                .as_provider()
+                .extension_ids()
+                .map_err(StashError::ReadProvider)?
+            {
+                let Some(witness_anchor) = extension_anchors.get(&opid) else {
+                    continue;
+                };
+                let extension = self
+                    .stash
+                    .as_provider()
+                    .extension(opid)
+                    .map_err(StashError::from)?;
+                if extension.contract_id == contract_id {
+                    history.add_extension(extension, *witness_anchor);
+                }
+            }
+
+            histories.push((contract_id, history));
+        }
+
+        for bundle_id in &rolled_back {
+            if !self.index.unindex_bundle(*bundle_id)? {
+                return Err(StockError::RollbackUnsupported(*bundle_id));
+            }
+        }
+        for (contract_id, history) in histories {
+            self.state
+                .create_or_update_state::<R>(contract_id, |_| Ok(history))?;
+        }
+
+        Ok(rolled_back)
+    }
+
     fn transition(&self, opid: OpId) -> Result<&Transition, StockError<S, H, P, ConsignError>> {
         let bundle_id = self.index.bundle_id_for_op(opid)?;
         let bundle = self.stash.bundle(bundle_id)?;
@@ -1136,3 +1599,674 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> Stock<S, H, P> {
         Ok(self.stash.store_secret_seal(seal)?)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+    use std::str::FromStr;
+
+    use amplify::confinement::{self, SmallOrdSet, SmallVec};
+    use amplify::ByteArray;
+    use bp::seals::txout::ExplicitSeal;
+    use bp::{Outpoint, Txid};
+    use invoice::{Pay2Vout, RgbInvoiceBuilder, XChainNet};
+    use rgb::validation::{ResolveWitness, WitnessResolverError};
+    use rgb::{
+        Assign, AssignmentType, Assignments, ExposedState, FungibleType, GenesisSeal, GraphSeal,
+        Input, InputMap, Occurrences, OwnedStateSchema, Schema, TransitionBundle, TransitionSchema,
+        TransitionType, TypedAssigns, VoidState, WitnessOrd, WitnessPos, XChain, XWitnessTx,
+    };
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+    use crate::containers::{BundleDichotomy, Contract, DisclosureBuilder, Kit, PubWitness};
+    use crate::interface::{
+        AssignIface, GenesisIface, Iface, IfaceImpl, NamedField, OwnedIface, Req, TransitionIface,
+    };
+    use crate::persistence::{ChangePerInput, IndexReadError, IndexWriteError};
+
+    struct NoResolver;
+    impl ResolveWitness for NoResolver {
+        fn resolve_pub_witness(
+            &self,
+            witness_id: XWitnessId,
+        ) -> Result<XWitnessTx, WitnessResolverError> {
+            Err(WitnessResolverError::Unknown(witness_id))
+        }
+    }
+
+    struct MempoolResolver;
+    impl ResolveHeight for MempoolResolver {
+        fn resolve_height(&mut self, witness_id: XWitnessId) -> Result<WitnessAnchor, String> {
+            Ok(WitnessAnchor::from_mempool(witness_id))
+        }
+    }
+
+    struct HeightResolver(BTreeMap<XWitnessId, u32>);
+    impl ResolveHeight for HeightResolver {
+        fn resolve_height(&mut self, witness_id: XWitnessId) -> Result<WitnessAnchor, String> {
+            let Some(height) = self.0.get(&witness_id) else {
+                return Ok(WitnessAnchor::from_mempool(witness_id));
+            };
+            Ok(WitnessAnchor {
+                witness_ord: WitnessOrd::OnChain(WitnessPos::new(*height, 1231006505).unwrap()),
+                witness_id,
+            })
+        }
+    }
+
+    /// Index provider which doesn't support unregistering bundles.
+    #[derive(Debug, Default)]
+    struct AppendOnlyIndex(MemIndex);
+
+    impl IndexProvider for AppendOnlyIndex {}
+
+    impl IndexReadProvider for AppendOnlyIndex {
+        type Error = Infallible;
+
+        fn contracts_assigning(
+            &self,
+            outputs: BTreeSet<XOutputSeal>,
+        ) -> Result<impl Iterator<Item = ContractId> + '_, Self::Error> {
+            self.0.contracts_assigning(outputs)
+        }
+
+        fn public_opouts(
+            &self,
+            contract_id: ContractId,
+        ) -> Result<BTreeSet<Opout>, IndexReadError<Self::Error>> {
+            self.0.public_opouts(contract_id)
+        }
+
+        fn opouts_by_outputs(
+            &self,
+            contract_id: ContractId,
+            outputs: impl IntoIterator<Item = impl Into<XOutputSeal>>,
+        ) -> Result<BTreeSet<Opout>, IndexReadError<Self::Error>> {
+            self.0.opouts_by_outputs(contract_id, outputs)
+        }
+
+        fn opouts_by_terminals(
+            &self,
+            terminals: impl IntoIterator<Item = XChain<SecretSeal>>,
+        ) -> Result<BTreeSet<Opout>, Self::Error> {
+            self.0.opouts_by_terminals(terminals)
+        }
+
+        fn bundle_id_for_op(&self, opid: OpId) -> Result<BundleId, IndexReadError<Self::Error>> {
+            self.0.bundle_id_for_op(opid)
+        }
+
+        fn bundle_info(
+            &self,
+            bundle_id: BundleId,
+        ) -> Result<(XWitnessId, ContractId), IndexReadError<Self::Error>> {
+            self.0.bundle_info(bundle_id)
+        }
+    }
+
+    impl IndexWriteProvider for AppendOnlyIndex {
+        type Error = confinement::Error;
+
+        fn register_contract(&mut self, contract_id: ContractId) -> Result<bool, Self::Error> {
+            self.0.register_contract(contract_id)
+        }
+
+        fn register_bundle(
+            &mut self,
+            bundle_id: BundleId,
+            witness_id: XWitnessId,
+            contract_id: ContractId,
+        ) -> Result<bool, IndexWriteError<Self::Error>> {
+            self.0.register_bundle(bundle_id, witness_id, contract_id)
+        }
+
+        fn register_operation(
+            &mut self,
+            opid: OpId,
+            bundle_id: BundleId,
+        ) -> Result<bool, IndexWriteError<Self::Error>> {
+            self.0.register_operation(opid, bundle_id)
+        }
+
+        fn index_genesis_assignments<State: ExposedState>(
+            &mut self,
+            contract_id: ContractId,
+            vec: &[Assign<State, GenesisSeal>],
+            opid: OpId,
+            type_id: AssignmentType,
+        ) -> Result<(), IndexWriteError<Self::Error>> {
+            self.0
+                .index_genesis_assignments(contract_id, vec, opid, type_id)
+        }
+
+        fn index_transition_assignments<State: ExposedState>(
+            &mut self,
+            contract_id: ContractId,
+            vec: &[Assign<State, GraphSeal>],
+            opid: OpId,
+            type_id: AssignmentType,
+            witness_id: XWitnessId,
+        ) -> Result<(), IndexWriteError<Self::Error>> {
+            self.0
+                .index_transition_assignments(contract_id, vec, opid, type_id, witness_id)
+        }
+    }
+
+    fn stock() -> (Stock, ContractId) { stock_with(MemIndex::default()) }
+
+    fn stock_with<P: IndexProvider>(index: P) -> (Stock<MemStash, MemState, P>, ContractId) {
+        let mut contract =
+            Contract::from_str(include_str!("../../asset/armored_contract.default")).unwrap();
+        contract.genesis.schema_id = contract.schema.schema_id();
+        let contract = contract.validate(&mut NoResolver, false).unwrap();
+        let contract_id = contract.contract_id();
+        let mut stock = Stock::with(MemStash::default(), MemState::default(), index);
+        stock
+            .import_contract(contract, &mut MempoolResolver)
+            .unwrap();
+        (stock, contract_id)
+    }
+
+    fn fungible_kit() -> Kit {
+        let owner = AssignmentType::with(1);
+        let transfer = TransitionType::with(1);
+
+        let mut schema = Schema::strict_dumb();
+        schema.owned_types = tiny_bmap! {
+            owner => OwnedStateSchema::Fungible(FungibleType::Unsigned64Bit),
+        };
+        schema.genesis.assignments = tiny_bmap! { owner => Occurrences::OnceOrMore };
+        schema.transitions = tiny_bmap! {
+            transfer => TransitionSchema {
+                metadata: none!(),
+                globals: none!(),
+                inputs: tiny_bmap! { owner => Occurrences::OnceOrMore },
+                assignments: tiny_bmap! { owner => Occurrences::OnceOrMore },
+                valencies: none!(),
+                validator: None,
+            },
+        };
+
+        let mut iface = Iface::strict_dumb();
+        iface.name = tn!("Fungible");
+        iface.assignments = tiny_bmap! {
+            fname!("owner") => AssignIface::private(OwnedIface::Amount, Req::OneOrMore),
+        };
+        iface.genesis = GenesisIface {
+            assignments: tiny_bmap! { fname!("owner") => Occurrences::OnceOrMore },
+            ..GenesisIface::strict_dumb()
+        };
+        iface.transitions = tiny_bmap! {
+            fname!("transfer") => TransitionIface {
+                inputs: tiny_bmap! { fname!("owner") => Occurrences::OnceOrMore },
+                assignments: tiny_bmap! { fname!("owner") => Occurrences::OnceOrMore },
+                default_assignment: Some(fname!("owner")),
+                ..TransitionIface::strict_dumb()
+            },
+        };
+        iface.default_operation = Some(fname!("transfer"));
+
+        let mut iimpl = IfaceImpl::strict_dumb();
+        iimpl.schema_id = schema.schema_id();
+        iimpl.iface_id = iface.iface_id();
+        iimpl.assignments = tiny_bset![NamedField::with(owner, fname!("owner"))];
+        iimpl.transitions = tiny_bset![NamedField::with(transfer, fname!("transfer"))];
+
+        Kit {
+            schemata: tiny_bset![schema],
+            ifaces: tiny_bset![iface],
+            iimpls: tiny_bset![iimpl],
+            ..Kit::default()
+        }
+    }
+
+    fn issue(stock: &mut Stock, allocations: &[(Outpoint, u64)]) -> ContractId {
+        let kit = fungible_kit();
+        let schema_id = kit.schemata.first().unwrap().schema_id();
+        stock.stash.consume_kit(kit).unwrap();
+        let mut builder = stock
+            .contract_builder("ssi:anonymous", schema_id, "Fungible")
+            .unwrap();
+        for (outpoint, amount) in allocations {
+            let seal =
+                GenesisSeal::new_random(CloseMethod::OpretFirst, outpoint.txid, outpoint.vout);
+            builder = builder
+                .add_fungible_state("owner", XChain::Bitcoin(seal), *amount)
+                .unwrap();
+        }
+        let contract = builder.issue_contract().unwrap();
+        let contract_id = contract.contract_id();
+        stock
+            .import_contract(contract, &mut MempoolResolver)
+            .unwrap();
+        contract_id
+    }
+
+    fn invoice(contract_id: ContractId, amount: u64) -> RgbInvoice {
+        let mut beneficiary = [0u8; 34];
+        beneficiary[0] = CloseMethod::OpretFirst as u8;
+        beneficiary[1] = 3;
+        let beneficiary = Beneficiary::WitnessVout(Pay2Vout::try_from(beneficiary).unwrap());
+        RgbInvoiceBuilder::with(contract_id, XChainNet::BitcoinTestnet(beneficiary))
+            .set_interface("Fungible")
+            .set_amount_raw(amount)
+            .finish()
+    }
+
+    fn transition(contract_id: ContractId, prev: u8, vout: u32) -> Transition {
+        let mut transition = Transition::strict_dumb();
+        transition.contract_id = contract_id;
+        transition.transition_type = TransitionType::with(1);
+        transition.inputs = SmallOrdSet::try_from_iter([Input::with(Opout::new(
+            OpId::from_byte_array([prev; 32]),
+            AssignmentType::with(1),
+            0,
+        ))])
+        .unwrap()
+        .into();
+        let seal = GraphSeal::with_blinded_vout(CloseMethod::OpretFirst, vout, 0);
+        transition.assignments = Assignments::from_inner(
+            Confined::try_from(BTreeMap::from([(
+                AssignmentType::with(1),
+                TypedAssigns::Declarative(
+                    SmallVec::try_from_iter([Assign::revealed(
+                        XChain::Bitcoin(seal),
+                        VoidState::default(),
+                    )])
+                    .unwrap(),
+                ),
+            )]))
+            .unwrap(),
+        );
+        transition
+    }
+
+    fn bundle(transitions: impl IntoIterator<Item = Transition>) -> TransitionBundle {
+        let known_transitions = transitions
+            .into_iter()
+            .map(|transition| (transition.id(), transition))
+            .collect::<BTreeMap<_, _>>();
+        TransitionBundle {
+            close_method: CloseMethod::OpretFirst,
+            input_map: InputMap::from(
+                Confined::try_from_iter(
+                    known_transitions
+                        .keys()
+                        .enumerate()
+                        .map(|(vin, opid)| (Vout::from_u32(vin as u32), *opid)),
+                )
+                .unwrap(),
+            ),
+            known_transitions: Confined::try_from(known_transitions).unwrap(),
+        }
+    }
+
+    fn fascia(contract_id: ContractId, txid: u8, bundle: TransitionBundle) -> Fascia {
+        Fascia {
+            witness: XChain::Bitcoin(PubWitness::new(Txid::from_byte_array([txid; 32]))),
+            anchor: strict_dumb!(),
+            bundles: Confined::try_from_iter([(contract_id, BundleDichotomy::with(bundle, None))])
+                .unwrap(),
+        }
+    }
+
+    fn vouts(stock: &Stock, contract_id: ContractId) -> BTreeSet<u32> {
+        stock
+            .contract_state(contract_id)
+            .unwrap()
+            .history
+            .rights()
+            .iter()
+            .map(|a| a.seal.as_reduced_unsafe().vout.to_u32())
+            .collect()
+    }
+
+    fn witnesses(stock: &Stock, contract_id: ContractId) -> BTreeSet<XWitnessId> {
+        stock
+            .contract_state(contract_id)
+            .unwrap()
+            .history
+            .rights()
+            .iter()
+            .filter_map(|a| match a.witness {
+                AssignmentWitness::Present(witness_id) => Some(witness_id),
+                AssignmentWitness::Absent => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rollback() {
+        let (mut stock, contract_id) = stock();
+        let first = bundle([transition(contract_id, 1, 0)]);
+        let second = bundle([transition(contract_id, 2, 1)]);
+        let (first_id, second_id) = (first.bundle_id(), second.bundle_id());
+        let first = fascia(contract_id, 1, first);
+        let second = fascia(contract_id, 2, second);
+        let w1 = first.witness_id();
+        let w2 = second.witness_id();
+        stock.consume_fascia(first).unwrap();
+        stock.consume_fascia(second.clone()).unwrap();
+        assert_eq!(witnesses(&stock, contract_id), bset![w1, w2]);
+        let seal = GraphSeal::with_blinded_vout(CloseMethod::OpretFirst, 1, 0);
+        let output = XChain::Bitcoin(seal).try_to_output_seal(w2).unwrap();
+        assert_eq!(
+            stock
+                .index
+                .opouts_by_outputs(contract_id, [output])
+                .unwrap()
+                .len(),
+            1
+        );
+
+        let rolled_back = stock.rollback(&bset![w2], &mut MempoolResolver).unwrap();
+        assert_eq!(rolled_back, bset![second_id]);
+        assert_eq!(witnesses(&stock, contract_id), bset![w1]);
+        assert!(stock.index.bundle_info(second_id).is_err());
+        assert!(stock
+            .index
+            .opouts_by_outputs(contract_id, [output])
+            .is_err());
+
+        // The bundle rolled back before must not be restored by later rollbacks
+        let rolled_back = stock.rollback(&bset![w1], &mut MempoolResolver).unwrap();
+        assert_eq!(rolled_back, bset![first_id]);
+        assert!(witnesses(&stock, contract_id).is_empty());
+
+        // Re-anchoring the bundle into a new witness indexes it again
+        let mut reanchored = second;
+        reanchored.witness = XChain::Bitcoin(PubWitness::new(Txid::from_byte_array([3; 32])));
+        let w3 = reanchored.witness_id();
+        stock.consume_fascia(reanchored).unwrap();
+        assert_eq!(witnesses(&stock, contract_id), bset![w3]);
+        assert_eq!(stock.index.bundle_info(second_id).unwrap(), (w3, contract_id));
+    }
+
+    #[test]
+    fn rollback_unsupported() {
+        let (mut stock, contract_id) = stock_with(AppendOnlyIndex::default());
+        let first = fascia(contract_id, 1, bundle([transition(contract_id, 1, 0)]));
+        let second = bundle([transition(contract_id, 2, 1)]);
+        let second_id = second.bundle_id();
+        let second = fascia(contract_id, 2, second);
+        let w2 = second.witness_id();
+        stock.consume_fascia(first).unwrap();
+        stock.consume_fascia(second).unwrap();
+        let state = stock.contract_state(contract_id).unwrap().history;
+
+        assert!(matches!(
+            stock.rollback(&bset![w2], &mut MempoolResolver),
+            Err(StockError::RollbackUnsupported(id)) if id == second_id
+        ));
+        assert_eq!(stock.contract_state(contract_id).unwrap().history, state);
+        assert_eq!(stock.index.bundle_info(second_id).unwrap(), (w2, contract_id));
+    }
+
+    #[test]
+    fn witness_statuses() {
+        let (mut stock, contract_id) = stock();
+        let first = fascia(contract_id, 1, bundle([transition(contract_id, 1, 0)]));
+        let second = fascia(contract_id, 2, bundle([transition(contract_id, 2, 1)]));
+        let w1 = first.witness_id();
+        let w2 = second.witness_id();
+        stock.consume_fascia(first).unwrap();
+        stock.consume_fascia(second).unwrap();
+        let mut resolver = HeightResolver(bmap! { w1 => 100 });
+
+        let statuses = stock
+            .witness_statuses(contract_id, &mut resolver, 101, 6)
+            .unwrap();
+        assert_eq!(statuses, bmap! {
+            AssignmentWitness::Present(w1) => WitnessStatus::Pending(2),
+            AssignmentWitness::Present(w2) => WitnessStatus::Unconfirmed,
+        });
+
+        // Statuses follow the chain tip without any update to the stock
+        let statuses = stock
+            .witness_statuses(contract_id, &mut resolver, 105, 6)
+            .unwrap();
+        assert_eq!(statuses[&AssignmentWitness::Present(w1)], WitnessStatus::Settled);
+
+        resolver.0.insert(w2, 105);
+        let statuses = stock
+            .witness_statuses(contract_id, &mut resolver, 105, 6)
+            .unwrap();
+        assert_eq!(statuses[&AssignmentWitness::Present(w2)], WitnessStatus::Pending(1));
+    }
+
+    #[test]
+    fn compose_change() {
+        let mut stock = Stock::default();
+        let txid = Txid::from_byte_array([0xAA; 32]);
+        let prev_outputs = [Outpoint::new(txid, 0), Outpoint::new(txid, 1)];
+        let contract_id = issue(&mut stock, &[(prev_outputs[0], 600), (prev_outputs[1], 400)]);
+        let prev_outputs =
+            prev_outputs.map(|o| XChain::Bitcoin(ExplicitSeal::new(CloseMethod::OpretFirst, o)));
+
+        let invoice = invoice(contract_id, 300);
+        let batch = stock
+            .compose_deterministic_with_change(
+                &invoice,
+                prev_outputs,
+                CloseMethod::OpretFirst,
+                Some(0u32),
+                |_, _, _, no| Some(Vout::from_u32(1 + no as u32)),
+                ChangePerInput,
+                |_, _, no| BlindingFactor::try_from([no as u8 + 1; 32]).unwrap(),
+                |_, _, no| no as u64,
+            )
+            .unwrap();
+        assert!(batch.blanks.is_empty());
+
+        let TypedAssigns::Fungible(assigns) = batch
+            .main
+            .transition
+            .assignments
+            .get(&AssignmentType::with(1))
+            .unwrap()
+        else {
+            panic!("fungible state expected");
+        };
+        let allocations = assigns
+            .iter()
+            .map(|assign| {
+                let (seal, state) = assign.to_revealed().unwrap();
+                (seal.as_reduced_unsafe().vout.to_u32(), state.value.as_u64())
+            })
+            .collect::<BTreeSet<_>>();
+        // Beneficiary and two equal change allocations, each on its own output
+        assert_eq!(allocations, bset![(0, 300), (2, 350), (3, 350)]);
+        let blindings = assigns
+            .iter()
+            .map(|assign| assign.to_revealed().unwrap().0.as_reduced_unsafe().blinding)
+            .collect::<BTreeSet<_>>();
+        assert_eq!(blindings.len(), 3);
+
+        let batch = stock
+            .compose_with_change(
+                &invoice,
+                prev_outputs,
+                CloseMethod::OpretFirst,
+                Some(0u32),
+                |_, _, _, no| Some(Vout::from_u32(1 + no as u32)),
+                ChangePerInput,
+            )
+            .unwrap();
+        let TypedAssigns::Fungible(assigns) = batch
+            .main
+            .transition
+            .assignments
+            .get(&AssignmentType::with(1))
+            .unwrap()
+        else {
+            panic!("fungible state expected");
+        };
+        let vouts = assigns
+            .iter()
+            .map(|assign| {
+                let (seal, _) = assign.to_revealed().unwrap();
+                seal.as_reduced_unsafe().vout.to_u32()
+            })
+            .collect::<BTreeSet<_>>();
+        assert_eq!(vouts, bset![0, 2, 3]);
+    }
+
+    #[test]
+    fn compose_deterministic() {
+        let mut stock = Stock::default();
+        let txid = Txid::from_byte_array([0xAA; 32]);
+        let prev_outputs = [Outpoint::new(txid, 0), Outpoint::new(txid, 1)];
+        let contract_id = issue(&mut stock, &[(prev_outputs[0], 600), (prev_outputs[1], 400)]);
+        let prev_outputs =
+            prev_outputs.map(|o| XChain::Bitcoin(ExplicitSeal::new(CloseMethod::OpretFirst, o)));
+
+        let blinded = RefCell::new(vec![]);
+        let batch = stock
+            .compose_deterministic(
+                &invoice(contract_id, 300),
+                prev_outputs,
+                CloseMethod::OpretFirst,
+                Some(0u32),
+                |_, _, _| Some(Vout::from_u32(1)),
+                |id, ty| {
+                    blinded.borrow_mut().push((id, ty));
+                    BlindingFactor::try_from([1; 32]).unwrap()
+                },
+                |_, _| 1,
+            )
+            .unwrap();
+        // All state is blinded for the invoiced assignment type, as before the
+        // change strategies were introduced
+        assert_eq!(blinded.into_inner(), vec![(contract_id, AssignmentType::with(1)); 2]);
+
+        let TypedAssigns::Fungible(assigns) = batch
+            .main
+            .transition
+            .assignments
+            .get(&AssignmentType::with(1))
+            .unwrap()
+        else {
+            panic!("fungible state expected");
+        };
+        let allocations = assigns
+            .iter()
+            .map(|assign| {
+                let (seal, state) = assign.to_revealed().unwrap();
+                (seal.as_reduced_unsafe().vout.to_u32(), state.value.as_u64())
+            })
+            .collect::<BTreeSet<_>>();
+        assert_eq!(allocations, bset![(0, 300), (1, 700)]);
+    }
+
+    #[test]
+    fn compose_blanks() {
+        let mut stock = Stock::default();
+        let outpoint = Outpoint::new(Txid::from_byte_array([0xAA; 32]), 0);
+        let main = issue(&mut stock, &[(outpoint, 100)]);
+        let other = issue(&mut stock, &[(outpoint, 200)]);
+        let prev_outputs = [XChain::Bitcoin(ExplicitSeal::new(CloseMethod::OpretFirst, outpoint))];
+
+        let blanks = stock
+            .compose_blanks(
+                prev_outputs,
+                Some(main),
+                "Fungible",
+                CloseMethod::OpretFirst,
+                Layer1::Bitcoin,
+                |_, _, _| Some(Vout::from_u32(1)),
+            )
+            .unwrap();
+        assert_eq!(blanks.len(), 1);
+        assert_eq!(blanks[0].transition.contract_id, other);
+        assert_eq!(blanks[0].transition.transition_type, TransitionType::BLANK);
+
+        let blanks = stock
+            .compose_blanks(
+                prev_outputs,
+                None,
+                "Fungible",
+                CloseMethod::OpretFirst,
+                Layer1::Bitcoin,
+                |_, _, _| Some(Vout::from_u32(1)),
+            )
+            .unwrap();
+        let contracts = blanks
+            .iter()
+            .map(|info| info.transition.contract_id)
+            .collect::<BTreeSet<_>>();
+        assert_eq!(contracts, bset![main, other]);
+
+        let batch = stock
+            .compose(
+                &invoice(main, 100),
+                prev_outputs,
+                CloseMethod::OpretFirst,
+                Some(0u32),
+                |_, _, _| Some(Vout::from_u32(1)),
+            )
+            .unwrap();
+        assert_eq!(batch.main.transition.contract_id, main);
+        assert_eq!(batch.blanks.len(), 1);
+        assert_eq!(batch.blanks[0].transition.contract_id, other);
+    }
+
+    #[test]
+    fn accept_disclosure() {
+        let (mut stock, contract_id) = stock();
+        let known = transition(contract_id, 1, 0);
+        let concealed = transition(contract_id, 2, 1);
+        let concealed_id = concealed.id();
+        let full = bundle([known.clone(), concealed]);
+        let mut partial = full.clone();
+        partial.known_transitions = Confined::try_from_iter([(known.id(), known)]).unwrap();
+        stock
+            .consume_fascia(fascia(contract_id, 1, partial))
+            .unwrap();
+        assert_eq!(vouts(&stock, contract_id), bset![0]);
+
+        let unrelated =
+            XChain::Bitcoin(GraphSeal::with_blinded_vout(CloseMethod::OpretFirst, 5u32, 0));
+        let disclosure = DisclosureBuilder::new()
+            .add_bundle(full.clone())
+            .unwrap()
+            .add_seal(unrelated)
+            .complete()
+            .unwrap();
+        let secret = unrelated.conceal();
+        assert!(matches!(
+            stock.accept_disclosure(disclosure, &mut MempoolResolver),
+            Err(StockError::InvalidInput(DisclosureError::UnrelatedSeal(seal))) if seal == secret
+        ));
+        assert_eq!(vouts(&stock, contract_id), bset![0]);
+
+        let mut forged = full.clone();
+        let mut transitions = forged.known_transitions.into_inner();
+        transitions.insert(concealed_id, transition(contract_id, 3, 2));
+        forged.known_transitions = Confined::try_from(transitions).unwrap();
+        let disclosure = DisclosureBuilder::new()
+            .add_bundle(forged)
+            .unwrap()
+            .complete()
+            .unwrap();
+        assert!(matches!(
+            stock.accept_disclosure(disclosure, &mut MempoolResolver),
+            Err(StockError::InvalidInput(DisclosureError::UnrelatedTransition(_, opid)))
+                if opid == concealed_id
+        ));
+        assert_eq!(vouts(&stock, contract_id), bset![0]);
+
+        let related =
+            XChain::Bitcoin(GraphSeal::with_blinded_vout(CloseMethod::OpretFirst, 1u32, 0));
+        let disclosure = DisclosureBuilder::new()
+            .add_bundle(full)
+            .unwrap()
+            .add_seal(related)
+            .complete()
+            .unwrap();
+        stock
+            .accept_disclosure(disclosure, &mut MempoolResolver)
+            .unwrap();
+        assert_eq!(vouts(&stock, contract_id), bset![0, 1]);
+    }
+}
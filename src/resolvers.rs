@@ -19,8 +19,233 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use rgb::{WitnessAnchor, XWitnessId};
+use std::collections::BTreeMap;
+
+use bp::Tx;
+use rgb::validation::{ResolveWitness, WitnessResolverError};
+use rgb::{WitnessAnchor, WitnessOrd, XWitnessId, XWitnessTx};
+
+use crate::containers::Consignment;
 
 pub trait ResolveHeight {
     fn resolve_height(&mut self, witness_id: XWitnessId) -> Result<WitnessAnchor, String>;
 }
+
+/// Mining status of a witness transaction, used to distinguish settled state
+/// from the state which may still be replaced or rolled back.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Display)]
+pub enum WitnessStatus {
+    /// Witness transaction is not mined and may be replaced (for instance with
+    /// RBF) or never mined.
+    #[display("unconfirmed")]
+    Unconfirmed,
+
+    /// Witness transaction is mined, but has less confirmations than required
+    /// to consider it final.
+    #[display("pending({0})")]
+    Pending(u32),
+
+    /// Witness transaction has at least the required number of confirmations.
+    #[display("settled")]
+    Settled,
+}
+
+impl WitnessStatus {
+    /// Detects witness status from its mining position relative to the
+    /// current blockchain tip.
+    pub fn with(ord: WitnessOrd, tip_height: u32, min_confirmations: u32) -> Self {
+        match ord {
+            WitnessOrd::OffChain => WitnessStatus::Unconfirmed,
+            WitnessOrd::OnChain(pos) => {
+                let confirmations = tip_height
+                    .saturating_sub(pos.height().get())
+                    .saturating_add(1);
+                if confirmations >= min_confirmations {
+                    WitnessStatus::Settled
+                } else {
+                    WitnessStatus::Pending(confirmations)
+                }
+            }
+        }
+    }
+
+    /// Returns number of confirmations, or `None` for the settled witnesses.
+    pub fn confirmations(self) -> Option<u32> {
+        match self {
+            WitnessStatus::Unconfirmed => Some(0),
+            WitnessStatus::Pending(confirmations) => Some(confirmations),
+            WitnessStatus::Settled => None,
+        }
+    }
+
+    pub fn is_settled(self) -> bool { self == WitnessStatus::Settled }
+}
+
+/// Witness resolver serving witness transactions which were fetched and
+/// verified before from a cache, and using the inner resolver only for the
+/// witnesses absent from the cache.
+pub struct CachedResolver<'cache, 'resolver, R: ResolveWitness> {
+    cache: &'cache BTreeMap<XWitnessId, XWitnessTx>,
+    inner: &'resolver R,
+}
+
+impl<'cache, 'resolver, R: ResolveWitness> CachedResolver<'cache, 'resolver, R> {
+    pub fn new(cache: &'cache BTreeMap<XWitnessId, XWitnessTx>, inner: &'resolver R) -> Self {
+        CachedResolver { cache, inner }
+    }
+}
+
+impl<'cache, 'resolver, R: ResolveWitness> ResolveWitness for CachedResolver<'cache, 'resolver, R> {
+    fn resolve_pub_witness(
+        &self,
+        witness_id: XWitnessId,
+    ) -> Result<XWitnessTx, WitnessResolverError> {
+        match self.cache.get(&witness_id) {
+            Some(tx) => Ok(tx.clone()),
+            None => self.inner.resolve_pub_witness(witness_id),
+        }
+    }
+}
+
+/// Offline witness resolver serving witness transactions which are bundled
+/// with the consignment (see [`crate::containers::PubWitness::tx`]) or
+/// provided separately, for instance from a sidecar file. Allows validation
+/// without access to any blockchain indexer.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct BundledTxResolver {
+    witnesses: BTreeMap<XWitnessId, XWitnessTx>,
+}
+
+impl BundledTxResolver {
+    /// Collects witness transactions bundled with the consignment. Bundled
+    /// transactions which do not match their witness id are ignored.
+    pub fn with_consignment<const TRANSFER: bool>(consignment: &Consignment<TRANSFER>) -> Self {
+        let mut resolver = Self::default();
+        for bw in &consignment.bundles {
+            let Some(tx) = bw.pub_witness.maybe_map_ref(|witness| witness.tx.clone()) else {
+                continue;
+            };
+            if tx.map_ref(Tx::txid) == bw.pub_witness.map_ref(|witness| witness.txid) {
+                resolver.add_witness(tx);
+            }
+        }
+        resolver
+    }
+
+    /// Adds witness transaction to the resolver, returning its id.
+    pub fn add_witness(&mut self, tx: XWitnessTx) -> XWitnessId {
+        let witness_id = tx.map_ref(Tx::txid);
+        self.witnesses.insert(witness_id, tx);
+        witness_id
+    }
+}
+
+impl ResolveWitness for BundledTxResolver {
+    fn resolve_pub_witness(
+        &self,
+        witness_id: XWitnessId,
+    ) -> Result<XWitnessTx, WitnessResolverError> {
+        self.witnesses
+            .get(&witness_id)
+            .cloned()
+            .ok_or(WitnessResolverError::Unknown(witness_id))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::confinement::LargeOrdSet;
+    use amplify::ByteArray;
+    use bp::{LockTime, Txid};
+    use rgb::XChain;
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+    use crate::containers::{BundledWitness, PubWitness, Transfer};
+
+    struct PanickingResolver;
+    impl ResolveWitness for PanickingResolver {
+        fn resolve_pub_witness(&self, _: XWitnessId) -> Result<XWitnessTx, WitnessResolverError> {
+            panic!("resolver must not be invoked")
+        }
+    }
+
+    struct NoResolver;
+    impl ResolveWitness for NoResolver {
+        fn resolve_pub_witness(
+            &self,
+            witness_id: XWitnessId,
+        ) -> Result<XWitnessTx, WitnessResolverError> {
+            Err(WitnessResolverError::Unknown(witness_id))
+        }
+    }
+
+    #[test]
+    fn bundled_tx_resolver() {
+        let mut tx = Tx::strict_dumb();
+        tx.lock_time = LockTime::from_consensus_u32(1);
+        let bundled = XWitnessTx::Bitcoin(tx);
+        let mut consignment = Transfer::strict_dumb();
+        consignment.bundles = LargeOrdSet::try_from_iter([
+            BundledWitness {
+                pub_witness: XChain::Bitcoin(PubWitness::with(bundled.as_reduced_unsafe().clone())),
+                anchored_bundles: strict_dumb!(),
+            },
+            BundledWitness {
+                pub_witness: XChain::Bitcoin(PubWitness {
+                    txid: Txid::from_byte_array([3; 32]),
+                    tx: Some(Tx::strict_dumb()),
+                    spv: none!(),
+                }),
+                anchored_bundles: strict_dumb!(),
+            },
+        ])
+        .unwrap();
+
+        let mut resolver = BundledTxResolver::with_consignment(&consignment);
+        let bundled_id = bundled.map_ref(Tx::txid);
+        assert_eq!(resolver.resolve_pub_witness(bundled_id).unwrap(), bundled);
+        let mismatched = XWitnessId::Bitcoin(Txid::from_byte_array([3; 32]));
+        assert!(matches!(
+            resolver.resolve_pub_witness(mismatched),
+            Err(WitnessResolverError::Unknown(id)) if id == mismatched
+        ));
+
+        let sidecar = XWitnessTx::strict_dumb();
+        let sidecar_id = resolver.add_witness(sidecar.clone());
+        assert_eq!(resolver.resolve_pub_witness(sidecar_id).unwrap(), sidecar);
+    }
+
+    #[test]
+    fn cached_resolver() {
+        let cached = XWitnessId::Bitcoin(Txid::from_byte_array([1; 32]));
+        let unknown = XWitnessId::Bitcoin(Txid::from_byte_array([2; 32]));
+        let cache = bmap! { cached => XWitnessTx::strict_dumb() };
+
+        let resolver = CachedResolver::new(&cache, &PanickingResolver);
+        assert_eq!(resolver.resolve_pub_witness(cached).unwrap(), XWitnessTx::strict_dumb());
+
+        let resolver = CachedResolver::new(&cache, &NoResolver);
+        assert!(matches!(
+            resolver.resolve_pub_witness(unknown),
+            Err(WitnessResolverError::Unknown(id)) if id == unknown
+        ));
+    }
+
+    #[test]
+    fn witness_status() {
+        let mined = |height| WitnessOrd::with_mempool_or_height(height, 1_700_000_000);
+
+        assert_eq!(WitnessStatus::with(WitnessOrd::OffChain, 100, 6), WitnessStatus::Unconfirmed);
+        assert_eq!(WitnessStatus::with(mined(100), 100, 6), WitnessStatus::Pending(1));
+        assert_eq!(WitnessStatus::with(mined(100), 104, 6), WitnessStatus::Pending(5));
+        assert_eq!(WitnessStatus::with(mined(100), 105, 6), WitnessStatus::Settled);
+        // Tip height lagging behind the witness height
+        assert_eq!(WitnessStatus::with(mined(100), 90, 6), WitnessStatus::Pending(1));
+        assert_eq!(WitnessStatus::with(mined(100), 90, 1), WitnessStatus::Settled);
+
+        assert_eq!(WitnessStatus::Unconfirmed.confirmations(), Some(0));
+        assert_eq!(WitnessStatus::Settled.confirmations(), None);
+        assert!(!WitnessStatus::Pending(3).is_settled());
+    }
+}
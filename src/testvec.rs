@@ -0,0 +1,245 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Deterministic test vectors for downstream integration tests.
+//!
+//! The generated consignments are structurally consistent (all transitions are
+//! bundled, anchored and connected to the genesis), but their state does not
+//! follow any schema rules, so they must not be expected to pass validation.
+
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use amplify::confinement::{Confined, LargeOrdSet, SmallOrdMap, SmallOrdSet, SmallVec};
+use amplify::{ByteArray, Wrapper};
+use bp::seals::txout::CloseMethod;
+use bp::Txid;
+use rgb::{
+    Assign, AssignmentType, Assignments, Genesis, GraphSeal, Input, InputMap, Operation, Opout,
+    Transition, TransitionBundle, TransitionType, TypedAssigns, VoidState, XChain,
+};
+use strict_encoding::StrictDumb;
+
+use crate::containers::{
+    AnchoredBundles, BundledWitness, PubWitness, Terminal, TerminalSeal, Transfer,
+};
+
+/// Assignment type used by the generated state transitions.
+pub const SAMPLE_ASSIGNMENT_TYPE: AssignmentType = AssignmentType::with(1);
+
+/// Returns sample transfer consignment without any state transitions.
+pub fn sample_consignment() -> Transfer {
+    Transfer::from_str(include_str!("../asset/armored_transfer.default"))
+        .expect("embedded sample consignment is valid")
+}
+
+/// Returns genesis of the [`sample_consignment`] contract.
+pub fn sample_genesis() -> Genesis { sample_consignment().genesis }
+
+/// Returns state transition spending the first output of the
+/// [`sample_genesis`] and assigning state to the first witness output.
+pub fn sample_transition() -> Transition {
+    let genesis = sample_genesis();
+    let seal = GraphSeal::with_blinded_vout(CloseMethod::OpretFirst, 0u32, 0);
+    transition(&genesis, [Opout::new(genesis.id(), SAMPLE_ASSIGNMENT_TYPE, 0)], seal)
+}
+
+/// Starts construction of a deterministic sample consignment from the `seed`.
+///
+/// The same seed and parameters always produce the same consignment.
+pub fn sample_consignment_with(seed: u64) -> SampleConsignment {
+    SampleConsignment {
+        seed,
+        bundles: 1,
+        transitions: 1,
+    }
+}
+
+/// Builder of deterministic sample consignments; see
+/// [`sample_consignment_with`].
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct SampleConsignment {
+    seed: u64,
+    bundles: usize,
+    transitions: usize,
+}
+
+impl SampleConsignment {
+    /// Sets number of the anchored bundles in the history, each spending the
+    /// state assigned by the previous one.
+    pub fn bundles(mut self, count: usize) -> Self {
+        self.bundles = count;
+        self
+    }
+
+    /// Sets number of the state transitions per bundle.
+    ///
+    /// # Panics
+    ///
+    /// If the count is zero.
+    pub fn transitions(mut self, count: usize) -> Self {
+        assert!(count > 0, "bundle must contain at least one state transition");
+        self.transitions = count;
+        self
+    }
+
+    /// Generates the consignment. State assigned by the last bundle is
+    /// exposed as the consignment terminals, while the seals of all other
+    /// bundles are concealed.
+    pub fn finish(self) -> Transfer {
+        let mut rng = SplitMix64(self.seed);
+        let mut consignment = sample_consignment();
+        let genesis = &consignment.genesis;
+
+        let mut prev = vec![genesis.id(); self.transitions];
+        let mut bundles = Vec::with_capacity(self.bundles);
+        let mut terminals = BTreeMap::new();
+        for _ in 0..self.bundles {
+            let mut known_transitions = BTreeMap::new();
+            let mut seals = BTreeMap::new();
+            for (no, opid) in prev.iter_mut().enumerate() {
+                let seal = GraphSeal::with_blinded_vout(
+                    CloseMethod::OpretFirst,
+                    no as u32,
+                    rng.next_u64(),
+                );
+                let input = Opout::new(*opid, SAMPLE_ASSIGNMENT_TYPE, 0);
+                let transition = transition(genesis, [input], seal);
+                *opid = transition.id();
+                seals.insert(*opid, seal);
+                known_transitions.insert(*opid, transition);
+            }
+            let input_map = known_transitions
+                .keys()
+                .enumerate()
+                .map(|(vin, opid)| ((vin as u32).into(), *opid));
+            let bundle = TransitionBundle {
+                close_method: CloseMethod::OpretFirst,
+                input_map: InputMap::from(
+                    Confined::try_from_iter(input_map).expect("too many transitions"),
+                ),
+                known_transitions: Confined::try_from(known_transitions)
+                    .expect("too many transitions"),
+            };
+            let mut txid = [0u8; 32];
+            for chunk in txid.chunks_mut(8) {
+                chunk.copy_from_slice(&rng.next_u64().to_le_bytes());
+            }
+            terminals = BTreeMap::from([(
+                bundle.bundle_id(),
+                seals
+                    .into_values()
+                    .map(|seal| XChain::Bitcoin(TerminalSeal::conceal_from(seal))),
+            )]);
+            bundles.push(BundledWitness {
+                pub_witness: XChain::Bitcoin(PubWitness::new(Txid::from_byte_array(txid))),
+                anchored_bundles: AnchoredBundles::Opret(strict_dumb!(), bundle),
+            });
+        }
+
+        consignment.bundles = LargeOrdSet::try_from_iter(bundles).expect("too many bundles");
+        consignment.terminals =
+            SmallOrdMap::try_from_iter(terminals.into_iter().map(|(bundle_id, seals)| {
+                let seals = SmallOrdSet::try_from_iter(seals).expect("too many terminals");
+                (bundle_id, Terminal { seals })
+            }))
+            .expect("single terminal bundle");
+        let expose = consignment.terminal_seals().collect();
+        consignment.finalize(&expose);
+        consignment
+    }
+}
+
+fn transition(
+    genesis: &Genesis,
+    inputs: impl IntoIterator<Item = Opout>,
+    seal: GraphSeal,
+) -> Transition {
+    let mut transition = Transition::strict_dumb();
+    transition.contract_id = genesis.contract_id();
+    transition.transition_type = TransitionType::with(1);
+    transition.inputs = SmallOrdSet::try_from_iter(inputs.into_iter().map(Input::with))
+        .expect("too many inputs")
+        .into();
+    transition.assignments = Assignments::from_inner(
+        Confined::try_from(BTreeMap::from([(
+            SAMPLE_ASSIGNMENT_TYPE,
+            TypedAssigns::Declarative(
+                SmallVec::try_from(vec![Assign::revealed(
+                    XChain::Bitcoin(seal),
+                    VoidState::default(),
+                )])
+                .expect("single assignment"),
+            ),
+        )]))
+        .expect("single assignment"),
+    );
+    transition
+}
+
+/// Minimal deterministic generator, so that the vectors do not depend on the
+/// implementation of any external random number generator.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sample_consignment_id() {
+        let consignment = sample_consignment_with(42).finish();
+        assert_eq!(
+            consignment.consignment_id(),
+            sample_consignment_with(42).finish().consignment_id()
+        );
+        assert_eq!(
+            consignment.consignment_id().to_string(),
+            "rgb:csg:lHhRUD$K-chpbysq-L55qJVQ-m0mrVpb-PEeRuZe-cLEdEXk#forest-bazooka-people"
+        );
+        assert_ne!(
+            consignment.consignment_id(),
+            sample_consignment_with(43).finish().consignment_id()
+        );
+    }
+
+    #[test]
+    fn sample_consignment_size() {
+        let consignment = sample_consignment_with(7)
+            .bundles(3)
+            .transitions(2)
+            .finish();
+        assert_eq!(consignment.bundles.len(), 3);
+        assert_eq!(consignment.terminal_seals().count(), 2);
+        assert!(consignment.check_connectivity().is_ok());
+        assert!(consignment.check_finalized().is_ok());
+        assert_eq!(sample_transition(), sample_transition());
+    }
+}